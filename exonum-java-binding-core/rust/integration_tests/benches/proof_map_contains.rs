@@ -0,0 +1,182 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares `ProofMapIndexProxy.nativeContainsKey` with `nativeGet`, which copies the value
+//! into a Java array, as called from the Java side.
+
+#![feature(test)]
+
+extern crate integration_tests;
+extern crate java_bindings;
+#[macro_use]
+extern crate lazy_static;
+extern crate test;
+
+use integration_tests::vm::create_vm_for_benchmarks;
+use java_bindings::jni::objects::JObject;
+use java_bindings::jni::{JNIEnv, JavaVM};
+use java_bindings::utils::Handle;
+use java_bindings::{
+    DumbExecutor, Java_com_exonum_binding_storage_database_MemoryDb_nativeCreate,
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateFork,
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateSnapshot,
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeFree,
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeMerge,
+    Java_com_exonum_binding_storage_database_Views_nativeFree,
+    Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeContainsKey,
+    Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeCreate,
+    Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeFree,
+    Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeGet,
+    Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativePut, JniExecutor, JniResult,
+};
+
+use std::sync::Arc;
+use test::{black_box, Bencher};
+
+const MAP_NAME: &str = "bench_map";
+const ENTRIES: u8 = 200;
+const VALUE_SIZE: usize = 1024;
+
+lazy_static! {
+    pub static ref VM: Arc<JavaVM> = create_vm_for_benchmarks();
+    pub static ref EXECUTOR: DumbExecutor = DumbExecutor::new(VM.clone());
+}
+
+#[bench]
+pub fn contains_key(b: &mut Bencher) {
+    EXECUTOR
+        .with_attached(|env: &JNIEnv| {
+            with_snapshot_map(env, |map| {
+                let key = env.byte_array_from_slice(&[ENTRIES / 2; 32])?;
+                b.iter(|| {
+                    black_box(
+                        Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeContainsKey(
+                            env.clone(),
+                            JObject::null(),
+                            map,
+                            key,
+                        ),
+                    )
+                });
+                Ok(())
+            })
+        }).unwrap();
+}
+
+#[bench]
+pub fn get(b: &mut Bencher) {
+    EXECUTOR
+        .with_attached(|env: &JNIEnv| {
+            with_snapshot_map(env, |map| {
+                let key = env.byte_array_from_slice(&[ENTRIES / 2; 32])?;
+                b.iter(|| {
+                    let value =
+                        Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeGet(
+                            env.clone(),
+                            JObject::null(),
+                            map,
+                            key,
+                        );
+                    black_box(!value.is_null());
+                    // The values are released, so that they do not pile up in the local frame.
+                    env.delete_local_ref(value.into()).unwrap();
+                });
+                Ok(())
+            })
+        }).unwrap();
+}
+
+/// Calls the function with a `ProofMapIndex` of a snapshot, which has `ENTRIES` entries
+/// with the values of `VALUE_SIZE` bytes.
+fn with_snapshot_map<F>(env: &JNIEnv, f: F) -> JniResult<()>
+where
+    F: FnOnce(Handle) -> JniResult<()>,
+{
+    let db = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreate(
+        env.clone(),
+        JObject::null().into(),
+    );
+    let fork = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateFork(
+        env.clone(),
+        JObject::null(),
+        db,
+    );
+    let fork_map = Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeCreate(
+        env.clone(),
+        JObject::null().into(),
+        env.new_string(MAP_NAME)?,
+        fork,
+    );
+    for i in 0..ENTRIES {
+        env.with_local_frame(2, || {
+            let key = env.byte_array_from_slice(&[i; 32])?;
+            let value = env.byte_array_from_slice(&vec![i; VALUE_SIZE])?;
+            Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativePut(
+                env.clone(),
+                JObject::null(),
+                fork_map,
+                key,
+                value,
+            );
+            Ok(JObject::null())
+        })?;
+    }
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeMerge(
+        env.clone(),
+        JObject::null(),
+        db,
+        fork,
+    );
+    Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeFree(
+        env.clone(),
+        JObject::null().into(),
+        fork_map,
+    );
+    Java_com_exonum_binding_storage_database_Views_nativeFree(
+        env.clone(),
+        JObject::null().into(),
+        fork,
+    );
+
+    let snapshot = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateSnapshot(
+        env.clone(),
+        JObject::null(),
+        db,
+    );
+    let map = Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeCreate(
+        env.clone(),
+        JObject::null().into(),
+        env.new_string(MAP_NAME)?,
+        snapshot,
+    );
+
+    let result = f(map);
+
+    Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeFree(
+        env.clone(),
+        JObject::null().into(),
+        map,
+    );
+    Java_com_exonum_binding_storage_database_Views_nativeFree(
+        env.clone(),
+        JObject::null().into(),
+        snapshot,
+    );
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeFree(
+        env.clone(),
+        JObject::null().into(),
+        db,
+    );
+    result
+}
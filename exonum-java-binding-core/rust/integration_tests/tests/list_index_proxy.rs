@@ -0,0 +1,186 @@
+extern crate integration_tests;
+extern crate java_bindings;
+#[macro_use]
+extern crate lazy_static;
+
+use integration_tests::vm::create_vm_for_tests;
+use java_bindings::jni::objects::JObject;
+use java_bindings::jni::sys::jlong;
+use java_bindings::jni::{JNIEnv, JavaVM};
+use java_bindings::utils::{
+    get_and_clear_java_exception, get_class_name, get_exception_message, Handle,
+};
+use java_bindings::{
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeCreate,
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateFork,
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeFree,
+    Java_com_exonum_binding_storage_database_Views_nativeFree,
+    Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeAdd,
+    Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeCreate,
+    Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeFree,
+    Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeGet,
+    Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeSet, JniExecutor, JniResult,
+    MainExecutor,
+};
+use std::i64;
+use std::sync::Arc;
+
+const INDEX_OUT_OF_BOUNDS_EXCEPTION_FQN: &str = "java.lang.IndexOutOfBoundsException";
+
+lazy_static! {
+    static ref VM: Arc<JavaVM> = create_vm_for_tests();
+    pub static ref EXECUTOR: MainExecutor = MainExecutor::new(VM.clone());
+}
+
+#[test]
+fn get_negative_index() {
+    EXECUTOR
+        .with_attached(|env: &JNIEnv| {
+            assert_eq!(
+                (
+                    INDEX_OUT_OF_BOUNDS_EXCEPTION_FQN.to_owned(),
+                    Some("Index must not be negative: -1".to_owned())
+                ),
+                get_and_get_exception(env, -1)?
+            );
+            Ok(())
+        }).unwrap();
+}
+
+#[test]
+fn get_max_index() {
+    EXECUTOR
+        .with_attached(|env: &JNIEnv| {
+            assert_eq!(
+                (
+                    INDEX_OUT_OF_BOUNDS_EXCEPTION_FQN.to_owned(),
+                    Some(format!(
+                        "Index {} is out of bounds for the list of size 1",
+                        i64::MAX
+                    ))
+                ),
+                get_and_get_exception(env, i64::MAX)?
+            );
+            Ok(())
+        }).unwrap();
+}
+
+#[test]
+fn set_negative_index() {
+    EXECUTOR
+        .with_attached(|env: &JNIEnv| {
+            assert_eq!(
+                (
+                    INDEX_OUT_OF_BOUNDS_EXCEPTION_FQN.to_owned(),
+                    Some("Index must not be negative: -1".to_owned())
+                ),
+                set_and_get_exception(env, -1)?
+            );
+            Ok(())
+        }).unwrap();
+}
+
+#[test]
+fn set_max_index() {
+    EXECUTOR
+        .with_attached(|env: &JNIEnv| {
+            assert_eq!(
+                (
+                    INDEX_OUT_OF_BOUNDS_EXCEPTION_FQN.to_owned(),
+                    Some(format!(
+                        "Index {} is out of bounds for the list of size 1",
+                        i64::MAX
+                    ))
+                ),
+                set_and_get_exception(env, i64::MAX)?
+            );
+            Ok(())
+        }).unwrap();
+}
+
+/// Calls `nativeGet` with the index on a list of a single element, and returns the class name
+/// and the message of the thrown exception.
+fn get_and_get_exception(env: &JNIEnv, index: jlong) -> JniResult<(String, Option<String>)> {
+    with_list(env, |list| {
+        Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeGet(
+            env.clone(),
+            JObject::null(),
+            list,
+            index,
+        );
+        take_exception(env)
+    })
+}
+
+/// Calls `nativeSet` with the index on a list of a single element, and returns the class name
+/// and the message of the thrown exception.
+fn set_and_get_exception(env: &JNIEnv, index: jlong) -> JniResult<(String, Option<String>)> {
+    with_list(env, |list| {
+        let value = env.byte_array_from_slice(&[2])?;
+        Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeSet(
+            env.clone(),
+            JObject::null(),
+            list,
+            index,
+            value,
+        );
+        take_exception(env)
+    })
+}
+
+fn take_exception(env: &JNIEnv) -> JniResult<(String, Option<String>)> {
+    let exception = get_and_clear_java_exception(env);
+    Ok((
+        get_class_name(env, exception)?,
+        get_exception_message(env, exception)?,
+    ))
+}
+
+/// Calls the function with a list of a single element in a fresh fork.
+fn with_list<F, R>(env: &JNIEnv, f: F) -> JniResult<R>
+where
+    F: FnOnce(Handle) -> JniResult<R>,
+{
+    let db = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreate(
+        env.clone(),
+        JObject::null().into(),
+    );
+    let fork = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateFork(
+        env.clone(),
+        JObject::null(),
+        db,
+    );
+    let name = env.new_string("test_list")?;
+    let list = Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeCreate(
+        env.clone(),
+        JObject::null().into(),
+        name,
+        fork,
+    );
+    let value = env.byte_array_from_slice(&[1])?;
+    Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeAdd(
+        env.clone(),
+        JObject::null(),
+        list,
+        value,
+    );
+
+    let result = f(list);
+
+    Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeFree(
+        env.clone(),
+        JObject::null().into(),
+        list,
+    );
+    Java_com_exonum_binding_storage_database_Views_nativeFree(
+        env.clone(),
+        JObject::null().into(),
+        fork,
+    );
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeFree(
+        env.clone(),
+        JObject::null().into(),
+        db,
+    );
+    result
+}
@@ -0,0 +1,286 @@
+extern crate integration_tests;
+extern crate java_bindings;
+#[macro_use]
+extern crate lazy_static;
+
+use integration_tests::vm::create_vm_for_tests;
+use java_bindings::jni::objects::JObject;
+use java_bindings::jni::sys::jbyteArray;
+use java_bindings::jni::{JNIEnv, JavaVM};
+use java_bindings::utils::{get_and_clear_java_exception, get_class_name, get_exception_message};
+use java_bindings::{
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeCreate,
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateFork,
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateSnapshot,
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeFree,
+    Java_com_exonum_binding_storage_database_Views_nativeFree,
+    Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreate,
+    Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateKeysIter,
+    Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeDrainInto,
+    Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFree,
+    Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeGet,
+    Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysIterFree,
+    Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysIterNext,
+    Java_com_exonum_binding_storage_indices_MapIndexProxy_nativePut, JniExecutor, JniResult,
+    MainExecutor,
+};
+use std::ptr;
+use std::sync::Arc;
+
+const NULL_POINTER_EXCEPTION_FQN: &str = "java.lang.NullPointerException";
+const RUNTIME_EXCEPTION_FQN: &str = "java.lang.RuntimeException";
+
+lazy_static! {
+    static ref VM: Arc<JavaVM> = create_vm_for_tests();
+    pub static ref EXECUTOR: MainExecutor = MainExecutor::new(VM.clone());
+}
+
+#[test]
+fn put_null_key() {
+    EXECUTOR
+        .with_attached(|env: &JNIEnv| {
+            let value = env.byte_array_from_slice(&[1])?;
+            assert_eq!(
+                (
+                    NULL_POINTER_EXCEPTION_FQN.to_owned(),
+                    Some("The key must not be null".to_owned())
+                ),
+                put_and_get_exception(env, ptr::null_mut(), value)?
+            );
+            Ok(())
+        }).unwrap();
+}
+
+#[test]
+fn put_null_value() {
+    EXECUTOR
+        .with_attached(|env: &JNIEnv| {
+            let key = env.byte_array_from_slice(&[1])?;
+            assert_eq!(
+                (
+                    NULL_POINTER_EXCEPTION_FQN.to_owned(),
+                    Some("The value must not be null".to_owned())
+                ),
+                put_and_get_exception(env, key, ptr::null_mut())?
+            );
+            Ok(())
+        }).unwrap();
+}
+
+#[test]
+fn guard_keeps_owned_view_alive() {
+    EXECUTOR
+        .with_attached(|env: &JNIEnv| {
+            let db = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreate(
+                env.clone(),
+                JObject::null().into(),
+            );
+            let fork = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateFork(
+                env.clone(),
+                JObject::null(),
+                db,
+            );
+            let name = env.new_string("test_map")?;
+            let map = Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreate(
+                env.clone(),
+                JObject::null().into(),
+                name,
+                fork,
+            );
+            let key = env.byte_array_from_slice(&[1])?;
+            let value = env.byte_array_from_slice(&[10])?;
+            Java_com_exonum_binding_storage_indices_MapIndexProxy_nativePut(
+                env.clone(),
+                JObject::null(),
+                map,
+                key,
+                value,
+            );
+            let iter = Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateKeysIter(
+                env.clone(),
+                JObject::null(),
+                map,
+            );
+
+            // The iterator handle keeps the fork alive after the map and the view are freed.
+            Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFree(
+                env.clone(),
+                JObject::null().into(),
+                map,
+            );
+            Java_com_exonum_binding_storage_database_Views_nativeFree(
+                env.clone(),
+                JObject::null().into(),
+                fork,
+            );
+
+            let first = Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysIterNext(
+                env.clone(),
+                JObject::null(),
+                iter,
+            );
+            assert_eq!(vec![1], env.convert_byte_array(first)?);
+            let next = Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysIterNext(
+                env.clone(),
+                JObject::null(),
+                iter,
+            );
+            assert!(next.is_null());
+
+            Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysIterFree(
+                env.clone(),
+                JObject::null(),
+                iter,
+            );
+            Java_com_exonum_binding_storage_database_MemoryDb_nativeFree(
+                env.clone(),
+                JObject::null().into(),
+                db,
+            );
+            Ok(())
+        }).unwrap();
+}
+
+#[test]
+fn drain_into_snapshot_keeps_source() {
+    EXECUTOR
+        .with_attached(|env: &JNIEnv| {
+            let db = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreate(
+                env.clone(),
+                JObject::null().into(),
+            );
+            let fork = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateFork(
+                env.clone(),
+                JObject::null(),
+                db,
+            );
+            let snapshot = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateSnapshot(
+                env.clone(),
+                JObject::null(),
+                db,
+            );
+            let src_name = env.new_string("src_map")?;
+            let src = Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreate(
+                env.clone(),
+                JObject::null().into(),
+                src_name,
+                fork,
+            );
+            let dst_name = env.new_string("dst_map")?;
+            let dst = Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreate(
+                env.clone(),
+                JObject::null().into(),
+                dst_name,
+                snapshot,
+            );
+            let key = env.byte_array_from_slice(&[1])?;
+            let value = env.byte_array_from_slice(&[10])?;
+            Java_com_exonum_binding_storage_indices_MapIndexProxy_nativePut(
+                env.clone(),
+                JObject::null(),
+                src,
+                key,
+                value,
+            );
+
+            Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeDrainInto(
+                env.clone(),
+                JObject::null(),
+                src,
+                dst,
+            );
+            let exception = get_and_clear_java_exception(env);
+            assert_eq!(RUNTIME_EXCEPTION_FQN, get_class_name(env, exception)?);
+
+            // The destination is checked first, so the source keeps its entries.
+            let stored = Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeGet(
+                env.clone(),
+                JObject::null(),
+                src,
+                key,
+            );
+            assert_eq!(vec![10], env.convert_byte_array(stored)?);
+
+            Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFree(
+                env.clone(),
+                JObject::null().into(),
+                dst,
+            );
+            Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFree(
+                env.clone(),
+                JObject::null().into(),
+                src,
+            );
+            Java_com_exonum_binding_storage_database_Views_nativeFree(
+                env.clone(),
+                JObject::null().into(),
+                snapshot,
+            );
+            Java_com_exonum_binding_storage_database_Views_nativeFree(
+                env.clone(),
+                JObject::null().into(),
+                fork,
+            );
+            Java_com_exonum_binding_storage_database_MemoryDb_nativeFree(
+                env.clone(),
+                JObject::null().into(),
+                db,
+            );
+            Ok(())
+        }).unwrap();
+}
+
+/// Puts the entry into a map of a fresh fork and returns the class name and the message
+/// of the exception thrown by `nativePut`.
+fn put_and_get_exception(
+    env: &JNIEnv,
+    key: jbyteArray,
+    value: jbyteArray,
+) -> JniResult<(String, Option<String>)> {
+    let db = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreate(
+        env.clone(),
+        JObject::null().into(),
+    );
+    let fork = Java_com_exonum_binding_storage_database_MemoryDb_nativeCreateFork(
+        env.clone(),
+        JObject::null(),
+        db,
+    );
+    let name = env.new_string("test_map")?;
+    let map = Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreate(
+        env.clone(),
+        JObject::null().into(),
+        name,
+        fork,
+    );
+
+    Java_com_exonum_binding_storage_indices_MapIndexProxy_nativePut(
+        env.clone(),
+        JObject::null(),
+        map,
+        key,
+        value,
+    );
+    let exception = get_and_clear_java_exception(env);
+    let result = (
+        get_class_name(env, exception)?,
+        get_exception_message(env, exception)?,
+    );
+
+    Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFree(
+        env.clone(),
+        JObject::null().into(),
+        map,
+    );
+    Java_com_exonum_binding_storage_database_Views_nativeFree(
+        env.clone(),
+        JObject::null().into(),
+        fork,
+    );
+    Java_com_exonum_binding_storage_database_MemoryDb_nativeFree(
+        env.clone(),
+        JObject::null().into(),
+        db,
+    );
+    Ok(result)
+}
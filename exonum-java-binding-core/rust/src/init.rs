@@ -12,14 +12,40 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use exonum::helpers;
+use exonum::helpers::{self, user_agent};
 use jni::objects::JClass;
-use jni::sys::jlong;
+use jni::sys::{jboolean, jint, jlong, jstring, JavaVM, JNI_TRUE, JNI_VERSION_1_8};
 use jni::JNIEnv;
+use serde_json;
 
+use std::os::raw::c_void;
 use std::panic;
+use std::ptr;
 
-use utils;
+use storage::{self, View};
+use utils::{self, Handle};
+
+/// The JNI version required by the library, see `JNI_OnLoad`.
+const JNI_VERSION: jint = JNI_VERSION_1_8;
+
+const HANDLE_TYPE_VIEW: jint = 0;
+const HANDLE_TYPE_MAP_INDEX: jint = 1;
+
+const PANIC_LOG_TARGET_STDERR: jint = 0;
+const PANIC_LOG_TARGET_LOG: jint = 1;
+
+const LOG_LEVEL_OFF: jint = 0;
+const LOG_LEVEL_ERROR: jint = 1;
+const LOG_LEVEL_WARN: jint = 2;
+const LOG_LEVEL_INFO: jint = 3;
+const LOG_LEVEL_DEBUG: jint = 4;
+
+/// Installs the panic hook when the library is loaded, see `utils::install_panic_hook`.
+#[no_mangle]
+pub extern "system" fn JNI_OnLoad(_vm: *mut JavaVM, _reserved: *mut c_void) -> jint {
+    utils::install_panic_hook();
+    JNI_VERSION
+}
 
 /// Performs the logger initialization.
 #[no_mangle]
@@ -44,3 +70,126 @@ pub extern "system" fn Java_com_exonum_binding_ClassNameTODO_nativeKnownHandles(
     let res = panic::catch_unwind(|| Ok(utils::known_handles() as jlong));
     utils::unwrap_exc_or_default(&env, res)
 }
+
+/// Enables or disables the metrics of the storage operations.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_ClassNameTODO_nativeEnableMetrics(
+    env: JNIEnv,
+    _: JClass,
+    enabled: jboolean,
+) {
+    let res = panic::catch_unwind(|| {
+        utils::set_metrics_enabled(enabled == JNI_TRUE);
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the counts and the total latencies in nanoseconds of the storage operations
+/// as a JSON object.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_ClassNameTODO_nativeDumpMetrics(
+    env: JNIEnv,
+    _: JClass,
+) -> jstring {
+    let res = panic::catch_unwind(|| Ok(env.new_string(utils::metrics_json())?.into_inner()));
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns a description of the native object identified by the given handle of the given type,
+/// or reports that the handle is invalid or freed. The type is one of `HANDLE_TYPE_*` constants.
+///
+/// The object is never accessed if the handle cannot be validated,
+/// see `utils::describe_handle`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_ClassNameTODO_nativeDescribeHandle(
+    env: JNIEnv,
+    _: JClass,
+    handle: Handle,
+    expected_type: jint,
+) -> jstring {
+    let res = panic::catch_unwind(|| {
+        let description = match expected_type {
+            HANDLE_TYPE_VIEW => utils::describe_handle::<View, _>(handle, View::describe),
+            HANDLE_TYPE_MAP_INDEX => storage::describe_map_index(handle),
+            _ => panic!("Unknown handle type: {}", expected_type),
+        };
+        Ok(env.new_string(description)?.into_inner())
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Sets the target of the native panic reports: the standard error stream
+/// (`PANIC_LOG_TARGET_STDERR`, the default) or the logger (`PANIC_LOG_TARGET_LOG`).
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_ClassNameTODO_nativeSetPanicLogTarget(
+    env: JNIEnv,
+    _: JClass,
+    target: jint,
+) {
+    let res = panic::catch_unwind(|| {
+        let target = match target {
+            PANIC_LOG_TARGET_STDERR => utils::PanicLogTarget::Stderr,
+            PANIC_LOG_TARGET_LOG => utils::PanicLogTarget::Log,
+            _ => panic!("Unknown panic log target: {}", target),
+        };
+        utils::set_panic_log_target(target);
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Sets the level of the native log messages of the bindings: one of `LOG_LEVEL_*` constants,
+/// from `LOG_LEVEL_OFF` to `LOG_LEVEL_DEBUG`. The default is `LOG_LEVEL_ERROR`.
+/// See `utils::set_log_level`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_util_LibraryFunctions_nativeSetLogLevel(
+    env: JNIEnv,
+    _: JClass,
+    level: jint,
+) {
+    let res = panic::catch_unwind(|| {
+        let level = match level {
+            LOG_LEVEL_OFF => utils::LogLevel::Off,
+            LOG_LEVEL_ERROR => utils::LogLevel::Error,
+            LOG_LEVEL_WARN => utils::LogLevel::Warn,
+            LOG_LEVEL_INFO => utils::LogLevel::Info,
+            LOG_LEVEL_DEBUG => utils::LogLevel::Debug,
+            _ => panic!("Unknown log level: {}", level),
+        };
+        utils::set_log_level(level);
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// The versions of the native library and its dependencies, see `nativeGetVersionInfo`.
+#[derive(Serialize)]
+struct VersionInfo {
+    /// The version of this crate.
+    version: &'static str,
+    /// The user agent of the linked Exonum: its version, the version of the compiler
+    /// and the OS.
+    exonum: String,
+    /// The JNI version required in `JNI_OnLoad`, e.g., `1.8`.
+    jni_version: String,
+}
+
+/// Returns the versions of the native library, the linked Exonum and the required JNI
+/// as a JSON object, which allows to detect the mismatches of the Java and native artifacts.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_util_LibraryFunctions_nativeGetVersionInfo(
+    env: JNIEnv,
+    _: JClass,
+) -> jstring {
+    let res = panic::catch_unwind(|| {
+        let info = VersionInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            exonum: user_agent::get(),
+            jni_version: format!("{}.{}", JNI_VERSION >> 16, JNI_VERSION & 0xffff),
+        };
+        let json = serde_json::to_string(&info).expect("Unable to serialize version info");
+        Ok(env.new_string(json)?.into_inner())
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
@@ -23,6 +23,7 @@
 // Function names must follow Java naming for the native functions.
 #![allow(non_snake_case)]
 
+extern crate backtrace;
 pub extern crate exonum;
 extern crate failure;
 pub extern crate jni;
@@ -33,6 +34,7 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 pub extern crate serde_json;
+extern crate tempdir;
 
 #[cfg(feature = "resource-manager")]
 #[macro_use]
@@ -0,0 +1,193 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use exonum::storage::{Database, Fork, MapIndex};
+use jni::objects::{JClass, JObject, JString};
+use jni::sys::jbyteArray;
+use jni::JNIEnv;
+
+use std::panic;
+
+use storage::db::{Key, Value};
+use storage::memorydb::MemoryDb;
+use storage::temporarydb::TemporaryDb;
+use utils::{self, Handle};
+
+/// An operation on a `MapIndex` with the given name.
+enum Operation {
+    Put(String, Key, Value),
+    Remove(String, Key),
+}
+
+/// A batch of operations on several `MapIndex`es, which are applied to a database atomically,
+/// as a single patch.
+#[derive(Default)]
+struct WriteBatch {
+    operations: Vec<Operation>,
+}
+
+impl WriteBatch {
+    /// Applies the accumulated operations to the fork in the order they were added,
+    /// leaving the batch empty.
+    fn apply(&mut self, fork: &mut Fork) {
+        for operation in self.operations.drain(..) {
+            match operation {
+                Operation::Put(name, key, value) => {
+                    MapIndex::new(name, &mut *fork).put(&key, value);
+                }
+                Operation::Remove(name, key) => {
+                    let mut map: MapIndex<_, Key, Value> = MapIndex::new(name, &mut *fork);
+                    map.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+/// Returns pointer to the created empty `WriteBatch` object.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_WriteBatchProxy_nativeCreate(
+    env: JNIEnv,
+    _: JClass,
+) -> Handle {
+    let res = panic::catch_unwind(|| Ok(utils::to_handle(WriteBatch::default())));
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Destroys the underlying `WriteBatch` object, discarding the operations that are not committed.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_WriteBatchProxy_nativeFree(
+    env: JNIEnv,
+    _: JClass,
+    batch_handle: Handle,
+) {
+    utils::drop_handle::<WriteBatch>(&env, batch_handle);
+}
+
+/// Adds an operation that sets `value` identified by the `key` into the `MapIndex`
+/// with the given name.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_WriteBatchProxy_nativePut(
+    env: JNIEnv,
+    _: JObject,
+    batch_handle: Handle,
+    name: JString,
+    key: jbyteArray,
+    value: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| {
+        let name = utils::convert_to_string(&env, name)?;
+        let key = env.convert_byte_array(key)?;
+        let value = env.convert_byte_array(value)?;
+        let batch = utils::cast_handle::<WriteBatch>(batch_handle);
+        batch.operations.push(Operation::Put(name, key, value));
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Adds an operation that removes value identified by the `key` from the `MapIndex`
+/// with the given name.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_WriteBatchProxy_nativeRemove(
+    env: JNIEnv,
+    _: JObject,
+    batch_handle: Handle,
+    name: JString,
+    key: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| {
+        let name = utils::convert_to_string(&env, name)?;
+        let key = env.convert_byte_array(key)?;
+        let batch = utils::cast_handle::<WriteBatch>(batch_handle);
+        batch.operations.push(Operation::Remove(name, key));
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Applies all the operations of the batch to the `MemoryDb` as a single patch, so either all
+/// of them are merged, or none. The batch is empty afterwards and can be reused.
+///
+/// `db_handle` must be a `MemoryDb`; use `nativeCommitToTemporaryDb` for a `TemporaryDb`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_WriteBatchProxy_nativeCommit(
+    env: JNIEnv,
+    _: JObject,
+    batch_handle: Handle,
+    db_handle: Handle,
+) {
+    let res = panic::catch_unwind(|| {
+        let batch = utils::cast_handle::<WriteBatch>(batch_handle);
+        let db = utils::cast_handle::<MemoryDb>(db_handle);
+        let mut fork = db.fork();
+        batch.apply(&mut fork);
+        db.merge(fork.into_patch()).expect("Unable to merge batch");
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Applies all the operations of the batch to the `TemporaryDb` as a single patch,
+/// see `nativeCommit`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_WriteBatchProxy_nativeCommitToTemporaryDb(
+    env: JNIEnv,
+    _: JObject,
+    batch_handle: Handle,
+    db_handle: Handle,
+) {
+    let res = panic::catch_unwind(|| {
+        let batch = utils::cast_handle::<WriteBatch>(batch_handle);
+        let db = utils::cast_handle::<TemporaryDb>(db_handle).database();
+        let mut fork = db.fork();
+        batch.apply(&mut fork);
+        db.merge(fork.into_patch()).expect("Unable to merge batch");
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum::storage::{Database, MemoryDB};
+
+    use super::*;
+
+    #[test]
+    fn apply_operations_in_order() {
+        let db = MemoryDB::new();
+        let mut batch = WriteBatch::default();
+        batch
+            .operations
+            .push(Operation::Put("first".to_owned(), vec![1], vec![10]));
+        batch
+            .operations
+            .push(Operation::Put("second".to_owned(), vec![2], vec![20]));
+        batch
+            .operations
+            .push(Operation::Remove("first".to_owned(), vec![1]));
+
+        let mut fork = db.fork();
+        batch.apply(&mut fork);
+        assert!(batch.operations.is_empty());
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let first: MapIndex<_, Key, Value> = MapIndex::new("first", &*snapshot);
+        let second: MapIndex<_, Key, Value> = MapIndex::new("second", &*snapshot);
+        assert_eq!(None, first.get(&vec![1]));
+        assert_eq!(Some(vec![20]), second.get(&vec![2]));
+    }
+}
@@ -12,15 +12,36 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use exonum::storage::{Fork, Snapshot};
-use jni::objects::JClass;
+use exonum::blockchain::Schema;
+use exonum::storage::{Change, Fork, Patch, Snapshot};
+use jni::objects::{JClass, JObject, JString};
+use jni::sys::{jboolean, jbyteArray, jlong};
 use jni::JNIEnv;
 
-use utils::{self, Handle};
+use std::ops::{Deref, DerefMut};
+use std::panic;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::sync::Arc;
+
+use storage::frozen_fork::FrozenFork;
+use storage::memorydb::BaseVersion;
+use utils::{self, FrameReader, Handle, ILLEGAL_ARGUMENT_EXCEPTION};
 
 pub(crate) type Key = Vec<u8>;
 pub(crate) type Value = Vec<u8>;
 
+/// The op-codes of the operations of `nativeReplayOps`.
+const REPLAY_OP_PUT: u8 = 0;
+const REPLAY_OP_REMOVE: u8 = 1;
+
+/// The id of the next created `View`.
+static NEXT_VIEW_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+/// The id of the next created savepoint, see `nativeCreateSavepoint`.
+static NEXT_SAVEPOINT_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+/// The generation of the forks, see `fork_generation`.
+static FORK_GENERATION: AtomicUsize = ATOMIC_USIZE_INIT;
+
 /// A `View` is a wrapper for `Snapshot` or `Fork`, which makes it possible to distinguish them
 /// on the rust side, and transfer them as a raw pointer to the java side.
 ///
@@ -36,9 +57,48 @@ pub(crate) type Value = Vec<u8>;
 
 pub(crate) struct View {
     // The `owned` field is used, but its value only needed for the drop stage,
-    // so `Box<Fork>`/`Box<Snapshot>` will be dropped when an instance of `View` leaves the scope.
-    _owned: Option<ViewOwned>,
+    // so `Box<Fork>`/`Box<Snapshot>` will be dropped when an instance of `View` and all
+    // the guards of it leave the scope.
+    _owned: Option<Arc<ViewOwned>>,
     reference: ViewRef,
+    // A process-unique id of the view, see `nativeSnapshotId`.
+    id: usize,
+    // The savepoints of the fork from the outermost to the innermost one.
+    savepoints: Vec<Savepoint>,
+    // The version of the database the fork was created at, see `MemoryDb::merge_checked`.
+    base_version: Option<BaseVersion>,
+    // The snapshot of the database the fork was created from, see `nativeFreezeFork`.
+    base_snapshot: Option<Arc<Snapshot>>,
+}
+
+/// A savepoint of a fork, see `nativeCreateSavepoint`.
+///
+/// `Fork` supports a single level of changes that can be rolled back: the changes
+/// after the last `flush`. The stack of savepoints is emulated on top of it:
+/// - the fork is flushed when the outermost savepoint is created, so `rollback`
+///   restores the state of the fork at the outermost savepoint;
+/// - each savepoint keeps a copy of the changes of the fork patch at its creation, so the state
+///   at an inner savepoint is restored by rolling back to the outermost one and applying
+///   the copied changes again. The keys not present in the copy were not modified before
+///   the savepoint, so they already have their values after the rollback.
+struct Savepoint {
+    id: jlong,
+    changes: Vec<(String, Key, Option<Value>)>,
+}
+
+/// A guard that keeps the owned `Snapshot` or `Fork` of a `View` alive, even if the `View`
+/// itself is destroyed.
+///
+/// The objects referring to a view, e.g., the indices and their iterators, hold a guard,
+/// so destroying the view on the java side before them does not leave them with
+/// dangling references. A guard of a `View` created from a reference does nothing.
+#[derive(Clone)]
+pub(crate) struct ViewGuard(Option<Arc<ViewOwned>>);
+
+/// A value referring to a view, along with the guard of that view.
+pub(crate) struct Guarded<T> {
+    value: T,
+    guard: ViewGuard,
 }
 
 enum ViewOwned {
@@ -56,7 +116,11 @@ impl View {
         View {
             // Make a "self-reference" to a value stored in the `owned` field.
             reference: unsafe { ViewRef::from_snapshot(&*snapshot) },
-            _owned: Some(ViewOwned::Snapshot(snapshot)),
+            _owned: Some(Arc::new(ViewOwned::Snapshot(snapshot))),
+            id: next_view_id(),
+            savepoints: Vec::new(),
+            base_version: None,
+            base_snapshot: None,
         }
     }
 
@@ -67,7 +131,11 @@ impl View {
         View {
             // Make a "self-reference" to a value stored in the `owned` field.
             reference: unsafe { ViewRef::from_fork(&mut *fork) },
-            _owned: Some(ViewOwned::Fork(fork)),
+            _owned: Some(Arc::new(ViewOwned::Fork(fork))),
+            id: next_view_id(),
+            savepoints: Vec::new(),
+            base_version: None,
+            base_snapshot: None,
         }
     }
 
@@ -77,6 +145,10 @@ impl View {
         View {
             reference: unsafe { ViewRef::from_snapshot(snapshot) },
             _owned: None,
+            id: next_view_id(),
+            savepoints: Vec::new(),
+            base_version: None,
+            base_snapshot: None,
         }
     }
 
@@ -86,12 +158,187 @@ impl View {
         View {
             reference: unsafe { ViewRef::from_fork(fork) },
             _owned: None,
+            id: next_view_id(),
+            savepoints: Vec::new(),
+            base_version: None,
+            base_snapshot: None,
         }
     }
 
     pub fn get(&mut self) -> &mut ViewRef {
         &mut self.reference
     }
+
+    /// Returns a guard keeping the owned part of this view alive.
+    pub fn guard(&self) -> ViewGuard {
+        ViewGuard(self._owned.clone())
+    }
+
+    /// Returns the version of the database this view was created at, if it is known.
+    pub fn base_version(&self) -> Option<&BaseVersion> {
+        self.base_version.as_ref()
+    }
+
+    /// Records the version of the database this view was created at.
+    pub fn set_base_version(&mut self, version: BaseVersion) {
+        self.base_version = Some(version);
+    }
+
+    /// Records the snapshot of the database the fork of this view was created from,
+    /// which must be taken along with the fork.
+    pub fn set_base_snapshot(&mut self, snapshot: Box<Snapshot>) {
+        self.base_snapshot = Some(Arc::from(snapshot));
+    }
+
+    /// Returns a snapshot of the current state of the fork.
+    fn freeze(&mut self) -> FrozenFork {
+        let base = self
+            .base_snapshot
+            .clone()
+            .expect("Unable to freeze a fork without a known base snapshot");
+        match self.reference {
+            ViewRef::Snapshot(_) => panic!("Attempt to freeze snapshot instead of fork."),
+            ViewRef::Fork(ref fork) => FrozenFork::new(base, fork.patch()),
+        }
+    }
+
+    /// Creates a savepoint of the fork and returns its id.
+    fn create_savepoint(&mut self) -> jlong {
+        let fork = owned_fork(&self._owned, &mut self.reference);
+        if self.savepoints.is_empty() {
+            fork.flush();
+        }
+        // The ids start with 1, so that 0 returned on failure is not a valid id.
+        let id = NEXT_SAVEPOINT_ID.fetch_add(1, Ordering::Relaxed) as jlong + 1;
+        let changes = patch_changes(fork.patch());
+        self.savepoints.push(Savepoint { id, changes });
+        id
+    }
+
+    /// Discards the changes of the fork made after the savepoint and the savepoints
+    /// created after it. The savepoint itself remains.
+    fn rollback_to(&mut self, id: jlong) {
+        let position = self.savepoint_position(id);
+        self.savepoints.truncate(position + 1);
+        let fork = owned_fork(&self._owned, &mut self.reference);
+        fork.rollback();
+        fork.flush();
+        advance_fork_generation();
+        if position != 0 {
+            for &(ref name, ref key, ref value) in &self.savepoints[position].changes {
+                match *value {
+                    Some(ref value) => fork.put(name, key.clone(), value.clone()),
+                    None => fork.remove(name, key.clone()),
+                }
+            }
+        }
+    }
+
+    /// Removes the savepoint and the savepoints created after it, keeping the changes.
+    fn release_savepoint(&mut self, id: jlong) {
+        let position = self.savepoint_position(id);
+        self.savepoints.truncate(position);
+    }
+
+    fn savepoint_position(&self, id: jlong) -> usize {
+        self.savepoints
+            .iter()
+            .position(|savepoint| savepoint.id == id)
+            .unwrap_or_else(|| panic!("Unknown savepoint: {}", id))
+    }
+
+    /// Returns a description of this view for the diagnostic purposes.
+    pub fn describe(&self) -> String {
+        let kind = match self.reference {
+            ViewRef::Snapshot(_) => "Snapshot",
+            ViewRef::Fork(_) => "Fork",
+        };
+        let ownership = if self._owned.is_some() {
+            "owned"
+        } else {
+            "native-owned"
+        };
+        format!("View of {} ({})", kind, ownership)
+    }
+}
+
+impl<T> Guarded<T> {
+    pub fn new(value: T, guard: ViewGuard) -> Self {
+        Guarded { value, guard }
+    }
+
+    /// Returns a guard of the view the value refers to.
+    pub fn guard(&self) -> ViewGuard {
+        self.guard.clone()
+    }
+}
+
+impl<T> Deref for Guarded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Guarded<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: Iterator> Iterator for Guarded<T> {
+    type Item = T::Item;
+
+    fn next(&mut self) -> Option<T::Item> {
+        self.value.next()
+    }
+}
+
+/// Returns the fork of a view for the savepoints. The view must own the fork: the forks
+/// provided by Exonum are flushed and rolled back by Exonum itself, so savepoints would
+/// break them.
+fn owned_fork<'a>(owned: &Option<Arc<ViewOwned>>, reference: &'a mut ViewRef) -> &'a mut Fork {
+    assert!(
+        owned.is_some(),
+        "Savepoints are not supported for the forks not owned by the view."
+    );
+    match *reference {
+        ViewRef::Snapshot(_) => panic!("Attempt to use savepoints of snapshot instead of fork."),
+        ViewRef::Fork(ref mut fork) => &mut **fork,
+    }
+}
+
+/// Returns a copy of the changes of the patch, with `None` for the removals.
+fn patch_changes(patch: &Patch) -> Vec<(String, Key, Option<Value>)> {
+    let mut result = Vec::new();
+    for (name, changes) in patch.iter() {
+        for (key, change) in changes.iter() {
+            let value = match *change {
+                Change::Put(ref value) => Some(value.clone()),
+                Change::Delete => None,
+            };
+            result.push((name.clone(), key.clone(), value));
+        }
+    }
+    result
+}
+
+/// Returns the current generation of the forks, which advances each time a fork is rolled back
+/// to a savepoint or replays operations. Such changes bypass the index objects, so the state
+/// they cache, e.g., the number of entries of a `MapIndex`, is discarded once the generation
+/// changes. The generation is shared by all the forks, so the caches of the indices
+/// of the other forks are discarded as well, which costs only a recount.
+pub(crate) fn fork_generation() -> usize {
+    FORK_GENERATION.load(Ordering::Relaxed)
+}
+
+fn advance_fork_generation() {
+    FORK_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+fn next_view_id() -> usize {
+    NEXT_VIEW_ID.fetch_add(1, Ordering::Relaxed)
 }
 
 impl ViewRef {
@@ -116,6 +363,274 @@ pub extern "system" fn Java_com_exonum_binding_storage_database_Views_nativeFree
     utils::drop_handle::<View>(&env, view_handle);
 }
 
+/// Returns the aggregated state hash of the view, i.e., the root hash of the state hash
+/// aggregator maintained by the core schema.
+///
+/// The aggregator is a `ProofMapIndex` that maps the key of each service table, derived
+/// from the service id and the table index with `Blockchain::service_table_unique_key`,
+/// to the root hash of that table. The service tables therefore contribute in the order
+/// of their keys' bits, not in the order of service ids. The aggregator is updated by the core
+/// when a block is created, so for a fork the hash reflects the state as of the last block.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_Views_nativeStateHash(
+    env: JNIEnv,
+    _: JClass,
+    view_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let hash = match *utils::cast_handle::<View>(view_handle).get() {
+            ViewRef::Snapshot(snapshot) => {
+                Schema::new(snapshot).state_hash_aggregator().merkle_root()
+            }
+            ViewRef::Fork(ref fork) => Schema::new(&**fork).state_hash_aggregator().merkle_root(),
+        };
+        utils::convert_hash(&env, &hash)
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns `true` if the view contains any data in the index with the given name.
+///
+/// As the indices in a family are stored under the family name, a family name is reported
+/// to exist if any of its indices contains data. An index is reported to not exist after
+/// it is cleared.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_Views_nativeIndexExists(
+    env: JNIEnv,
+    _: JClass,
+    name: JString,
+    view_handle: Handle,
+) -> jboolean {
+    let res = panic::catch_unwind(|| {
+        let name = utils::convert_to_string(&env, name)?;
+        let exists = match *utils::cast_handle::<View>(view_handle).get() {
+            ViewRef::Snapshot(snapshot) => snapshot.iter(&name, &[]).next().is_some(),
+            ViewRef::Fork(ref fork) => fork.iter(&name, &[]).next().is_some(),
+        };
+        Ok(exists as jboolean)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the id of the snapshot, assigned when it is created. The ids increase
+/// monotonically within the process, so the same snapshot always has the same id,
+/// and different snapshots have different ids, even if they have the same state.
+///
+/// The state of a fork changes, so an exception is thrown for a fork instead.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_Views_nativeSnapshotId(
+    env: JNIEnv,
+    _: JClass,
+    view_handle: Handle,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        let view = utils::cast_handle::<View>(view_handle);
+        let id = view.id;
+        match *view.get() {
+            ViewRef::Snapshot(_) => Ok(id as jlong),
+            ViewRef::Fork(_) => panic!("Attempt to get id of fork instead of snapshot."),
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns `true` if the view is a fork, which can be modified, or `false` if it is a snapshot.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_Views_nativeCanModify(
+    env: JNIEnv,
+    _: JClass,
+    view_handle: Handle,
+) -> jboolean {
+    let res = panic::catch_unwind(|| match *utils::cast_handle::<View>(view_handle).get() {
+        ViewRef::Snapshot(_) => Ok(false as jboolean),
+        ViewRef::Fork(_) => Ok(true as jboolean),
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns `true` if the fork has any changes, i.e., its patch contains at least one
+/// put or removal.
+///
+/// As the databases merge a copy of the fork patch, a merged fork remains dirty.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_Fork_nativeIsDirty(
+    env: JNIEnv,
+    _: JObject,
+    fork_handle: Handle,
+) -> jboolean {
+    let res = panic::catch_unwind(|| match *utils::cast_handle::<View>(fork_handle).get() {
+        ViewRef::Snapshot(_) => panic!("Attempt to check changes of snapshot instead of fork."),
+        ViewRef::Fork(ref fork) => Ok((fork.patch().len() != 0) as jboolean),
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns an approximate number of bytes buffered in the pending changes of the fork:
+/// the total length of the index names, keys and the put values in its patch.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_Fork_nativeMemoryUsage(
+    env: JNIEnv,
+    _: JObject,
+    fork_handle: Handle,
+) -> jlong {
+    let res = panic::catch_unwind(|| match *utils::cast_handle::<View>(fork_handle).get() {
+        ViewRef::Snapshot(_) => panic!("Attempt to get memory usage of snapshot instead of fork."),
+        ViewRef::Fork(ref fork) => Ok(patch_size_bytes(fork.patch()) as jlong),
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Creates a savepoint of the fork and returns its id. The savepoints form a stack:
+/// rolling back to a savepoint discards the changes made after it, including those
+/// after the inner savepoints, and keeps the changes made before it.
+///
+/// Panics if the view is a snapshot or a fork provided by Exonum rather than created
+/// by the Java side, as Exonum flushes and rolls back such forks itself.
+/// See `Savepoint` for the implementation details.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_Fork_nativeCreateSavepoint(
+    env: JNIEnv,
+    _: JObject,
+    fork_handle: Handle,
+) -> jlong {
+    let res =
+        panic::catch_unwind(|| Ok(utils::cast_handle::<View>(fork_handle).create_savepoint()));
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Discards the changes of the fork made after the savepoint with the given id, and removes
+/// the savepoints created after it. The savepoint remains, so it can be rolled back to again.
+///
+/// Panics if there is no such savepoint.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_Fork_nativeRollbackTo(
+    env: JNIEnv,
+    _: JObject,
+    fork_handle: Handle,
+    savepoint_id: jlong,
+) {
+    let res = panic::catch_unwind(|| {
+        utils::cast_handle::<View>(fork_handle).rollback_to(savepoint_id);
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Removes the savepoint with the given id and the savepoints created after it,
+/// keeping all the changes of the fork.
+///
+/// Panics if there is no such savepoint.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_Fork_nativeReleaseSavepoint(
+    env: JNIEnv,
+    _: JObject,
+    fork_handle: Handle,
+    savepoint_id: jlong,
+) {
+    let res = panic::catch_unwind(|| {
+        utils::cast_handle::<View>(fork_handle).release_savepoint(savepoint_id);
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns pointer to a snapshot of the current state of the fork, including its changes.
+///
+/// The subsequent changes of the fork are not reflected in the snapshot, and the snapshot
+/// remains valid after the fork is destroyed, so it can be read by another thread while
+/// the fork is modified. The changes of the fork are copied.
+///
+/// Panics if the fork is not created by `MemoryDb` or `TemporaryDb`, e.g., is provided
+/// by Exonum, as the snapshot the fork is created from must be known.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_Fork_nativeFreezeFork(
+    env: JNIEnv,
+    _: JObject,
+    fork_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let frozen: Box<Snapshot> = Box::new(utils::cast_handle::<View>(fork_handle).freeze());
+        Ok(utils::to_handle(View::from_owned_snapshot(frozen)))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Applies the recorded log of operations to the fork in order.
+///
+/// Each operation is an op-code byte followed by the frames of the index name and the key,
+/// and for `REPLAY_OP_PUT` the value frame, where a frame is the length of the bytes as
+/// a big-endian `u32` followed by the bytes. The operations are applied to the keys
+/// of the index as they are stored, which are the keys of a `MapIndex` and the like;
+/// `REPLAY_OP_REMOVE` of a missing key does nothing.
+///
+/// The whole log is parsed before any operation is applied, so the fork is not modified
+/// and `IllegalArgumentException` is thrown if the log has an unknown op-code, an invalid
+/// index name or is truncated. Panics if the view is a snapshot.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_Fork_nativeReplayOps(
+    env: JNIEnv,
+    _: JObject,
+    fork_handle: Handle,
+    ops: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| {
+        let data = env.convert_byte_array(ops)?;
+        let fork = match *utils::cast_handle::<View>(fork_handle).get() {
+            ViewRef::Snapshot(_) => panic!("Unable to modify snapshot."),
+            ViewRef::Fork(ref mut fork) => fork,
+        };
+        let ops = parse_ops(&data)
+            .or_else(|message| utils::throw_and_fail(&env, ILLEGAL_ARGUMENT_EXCEPTION, message))?;
+        for (name, key, value) in ops {
+            match value {
+                Some(value) => fork.put(&name, key, value),
+                None => fork.remove(&name, key),
+            }
+        }
+        advance_fork_generation();
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Parses the log of operations of `nativeReplayOps` into the index names, keys and values
+/// to put, or `None` for removals.
+fn parse_ops(data: &[u8]) -> Result<Vec<(String, Key, Option<Value>)>, String> {
+    const TRUNCATED: &str = "Truncated operation log";
+    let mut reader = FrameReader::new(data);
+    let mut ops = Vec::new();
+    while !reader.is_empty() {
+        let op = reader.read_u8().ok_or(TRUNCATED)?;
+        if op != REPLAY_OP_PUT && op != REPLAY_OP_REMOVE {
+            return Err(format!("Unknown op-code: {}", op));
+        }
+        let name = reader.read_frame().ok_or(TRUNCATED)?;
+        let name = String::from_utf8(name.to_vec()).map_err(|_| "Invalid index name")?;
+        let key = reader.read_frame().ok_or(TRUNCATED)?.to_vec();
+        let value = if op == REPLAY_OP_PUT {
+            Some(reader.read_frame().ok_or(TRUNCATED)?.to_vec())
+        } else {
+            None
+        };
+        ops.push((name, key, value));
+    }
+    Ok(ops)
+}
+
+fn patch_size_bytes(patch: &Patch) -> usize {
+    patch
+        .iter()
+        .map(|(name, changes)| {
+            let changes_size: usize = changes
+                .iter()
+                .map(|(key, change)| match *change {
+                    Change::Put(ref value) => key.len() + value.len(),
+                    Change::Delete => key.len(),
+                }).sum();
+            name.len() + changes_size
+        }).sum()
+}
+
 #[cfg(test)]
 mod tests {
     use exonum::storage::{Database, Entry, MemoryDB};
@@ -125,6 +640,36 @@ mod tests {
     use super::*;
 
     const TEST_VALUE: i32 = 42;
+    const SAVEPOINTS_INDEX: &str = "savepoints";
+
+    #[test]
+    fn parse_replay_ops() {
+        let mut data = vec![REPLAY_OP_PUT];
+        utils::write_frame(&mut data, b"map");
+        utils::write_frame(&mut data, &[1]);
+        utils::write_frame(&mut data, &[10]);
+        data.push(REPLAY_OP_REMOVE);
+        utils::write_frame(&mut data, b"map");
+        utils::write_frame(&mut data, &[2]);
+
+        assert_eq!(
+            Ok(vec![
+                ("map".to_owned(), vec![1], Some(vec![10])),
+                ("map".to_owned(), vec![2], None),
+            ]),
+            parse_ops(&data)
+        );
+    }
+
+    #[test]
+    fn parse_invalid_replay_ops() {
+        assert_eq!(Err("Unknown op-code: 2".to_owned()), parse_ops(&[2]));
+
+        let mut data = vec![REPLAY_OP_PUT];
+        utils::write_frame(&mut data, b"map");
+        utils::write_frame(&mut data, &[1]);
+        assert_eq!(Err("Truncated operation log".to_owned()), parse_ops(&data));
+    }
 
     #[test]
     fn create_view_with_owned_fork() {
@@ -162,15 +707,107 @@ mod tests {
         assert!(view._owned.is_none());
     }
 
+    #[test]
+    fn patch_size() {
+        let db = setup_database();
+        let mut fork = db.fork();
+        assert_eq!(0, patch_size_bytes(fork.patch()));
+        fork.put("name", vec![1, 2], vec![3, 4, 5]);
+        fork.remove("name", vec![6]);
+        assert_eq!(4 + 2 + 3 + 1, patch_size_bytes(fork.patch()));
+    }
+
+    #[test]
+    fn nested_savepoints() {
+        let db = setup_database();
+        let mut view = View::from_owned_fork(db.fork());
+        let outer = view.create_savepoint();
+        put(&mut view, vec![1], Some(vec![10]));
+        let inner = view.create_savepoint();
+        put(&mut view, vec![1], Some(vec![11]));
+        put(&mut view, vec![2], Some(vec![20]));
+        put(&mut view, vec![3], None);
+
+        view.rollback_to(inner);
+        assert_eq!(Some(vec![10]), get(&mut view, vec![1]));
+        assert_eq!(None, get(&mut view, vec![2]));
+        assert_eq!(Some(vec![3]), get(&mut view, vec![3]));
+
+        put(&mut view, vec![2], Some(vec![21]));
+        view.rollback_to(outer);
+        assert_eq!(None, get(&mut view, vec![1]));
+        assert_eq!(None, get(&mut view, vec![2]));
+
+        put(&mut view, vec![1], Some(vec![12]));
+        view.release_savepoint(outer);
+        assert!(view.savepoints.is_empty());
+        assert_eq!(Some(vec![12]), get(&mut view, vec![1]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown savepoint")]
+    fn rollback_to_released_savepoint() {
+        let db = setup_database();
+        let mut view = View::from_owned_fork(db.fork());
+        let outer = view.create_savepoint();
+        let inner = view.create_savepoint();
+        view.release_savepoint(outer);
+        view.rollback_to(inner);
+    }
+
+    #[test]
+    fn rollback_advances_fork_generation() {
+        let db = setup_database();
+        let mut view = View::from_owned_fork(db.fork());
+        let savepoint = view.create_savepoint();
+        let generation = fork_generation();
+        view.rollback_to(savepoint);
+        assert_ne!(generation, fork_generation());
+    }
+
+    #[test]
+    fn view_ids_are_unique() {
+        let db = setup_database();
+        let first = View::from_owned_snapshot(db.snapshot());
+        let second = View::from_owned_snapshot(db.snapshot());
+        assert!(first.id < second.id);
+    }
+
+    #[test]
+    fn guard_of_ref_view() {
+        let db = setup_database();
+        let snapshot = db.snapshot();
+        let view = View::from_ref_snapshot(&*snapshot);
+        assert!(view.guard().0.is_none());
+    }
+
     // Creates database with a prepared state.
     fn setup_database() -> MemoryDB {
         let db = MemoryDB::new();
         let mut fork = db.fork();
         entry(&mut fork).set(TEST_VALUE);
+        fork.put(SAVEPOINTS_INDEX, vec![3], vec![3]);
         db.merge(fork.into_patch()).unwrap();
         db
     }
 
+    fn put(view: &mut View, key: Key, value: Option<Value>) {
+        match *view.get() {
+            ViewRef::Fork(ref mut fork) => match value {
+                Some(value) => fork.put(SAVEPOINTS_INDEX, key, value),
+                None => fork.remove(SAVEPOINTS_INDEX, key),
+            },
+            _ => panic!("View::reference expected to be Fork"),
+        }
+    }
+
+    fn get(view: &mut View, key: Key) -> Option<Value> {
+        match *view.get() {
+            ViewRef::Fork(ref fork) => fork.get(SAVEPOINTS_INDEX, &key),
+            _ => panic!("View::reference expected to be Fork"),
+        }
+    }
+
     fn entry<T>(view: T) -> Entry<T, i32>
     where
         T: AsRef<Snapshot + 'static>,
@@ -208,7 +845,7 @@ mod tests {
             ViewRef::Snapshot(ref s) => Ptr::Snapshot(&**s),
         };
 
-        let owned = match *view._owned.as_ref().unwrap() {
+        let owned = match **view._owned.as_ref().unwrap() {
             ViewOwned::Fork(ref f) => Ptr::Fork(&**f),
             ViewOwned::Snapshot(ref s) => Ptr::Snapshot(&**s),
         };
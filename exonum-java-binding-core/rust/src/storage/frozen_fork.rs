@@ -0,0 +1,220 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use exonum::storage::{Change, Iter, Iterator as StorageIterator, Patch, Snapshot};
+
+use std::cmp::Ordering;
+use std::collections::btree_map::Range;
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::sync::Arc;
+
+use storage::db::{Key, Value};
+
+/// The changes of a column family: the values put, or `None` for the removed keys.
+type Changes = BTreeMap<Key, Option<Value>>;
+
+/// A snapshot of the state of a fork at some moment, see `nativeFreezeFork`.
+///
+/// `Fork` does not give access to the snapshot it is created from, so the frozen fork
+/// keeps a snapshot of the database taken along with the fork, and a copy of the changes
+/// of the fork applied on top of it. The subsequent changes of the fork are not reflected
+/// in the frozen fork.
+pub(crate) struct FrozenFork {
+    base: Arc<Snapshot>,
+    changes: BTreeMap<String, Changes>,
+}
+
+impl FrozenFork {
+    /// Creates a frozen fork from the snapshot the fork is created from and its patch.
+    pub fn new(base: Arc<Snapshot>, patch: &Patch) -> Self {
+        let mut changes = BTreeMap::new();
+        for (name, patch_changes) in patch.iter() {
+            let name_changes: Changes = patch_changes
+                .iter()
+                .map(|(key, change)| match *change {
+                    Change::Put(ref value) => (key.clone(), Some(value.clone())),
+                    Change::Delete => (key.clone(), None),
+                }).collect();
+            changes.insert(name.clone(), name_changes);
+        }
+        FrozenFork { base, changes }
+    }
+}
+
+impl Snapshot for FrozenFork {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        match self.changes.get(name).and_then(|changes| changes.get(key)) {
+            Some(change) => change.clone(),
+            None => self.base.get(name, key),
+        }
+    }
+
+    fn iter<'a>(&'a self, name: &str, from: &[u8]) -> Iter<'a> {
+        let base = self.base.iter(name, from);
+        match self.changes.get(name) {
+            Some(changes) => Box::new(FrozenForkIter {
+                base,
+                changes: changes.range(from.to_vec()..).peekable(),
+            }),
+            None => base,
+        }
+    }
+}
+
+/// An iterator merging the entries of the base snapshot with the changes of a frozen fork.
+struct FrozenForkIter<'a> {
+    base: Iter<'a>,
+    changes: Peekable<Range<'a, Key, Option<Value>>>,
+}
+
+/// The source of the next entry of `FrozenForkIter`.
+#[derive(Clone, Copy, PartialEq)]
+enum Source {
+    Base,
+    Changes,
+    // The key is both in the base snapshot and the changes, which take precedence.
+    Both,
+    Finished,
+}
+
+impl<'a> FrozenForkIter<'a> {
+    /// Skips the removed keys and returns the source of the next entry.
+    fn skip_removed(&mut self) -> Source {
+        loop {
+            let source = {
+                let base_key = self.base.peek().map(|(key, _)| key);
+                match (base_key, self.changes.peek()) {
+                    (None, None) => Source::Finished,
+                    (Some(_), None) => Source::Base,
+                    (None, Some(_)) => Source::Changes,
+                    (Some(base_key), Some(&(key, _))) => match base_key.cmp(key.as_slice()) {
+                        Ordering::Less => Source::Base,
+                        Ordering::Equal => Source::Both,
+                        Ordering::Greater => Source::Changes,
+                    },
+                }
+            };
+            let removed = match source {
+                Source::Changes | Source::Both => self
+                    .changes
+                    .peek()
+                    .map_or(false, |&(_, value)| value.is_none()),
+                Source::Base | Source::Finished => false,
+            };
+            if !removed {
+                return source;
+            }
+            if source == Source::Both {
+                self.base.next();
+            }
+            self.changes.next();
+        }
+    }
+}
+
+impl<'a> StorageIterator for FrozenForkIter<'a> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        match self.skip_removed() {
+            Source::Finished => None,
+            Source::Base => self.base.next(),
+            source => {
+                if source == Source::Both {
+                    self.base.next();
+                }
+                self.changes.next().map(|(key, value)| {
+                    let value = value.as_ref().expect("Removed key");
+                    (key.as_slice(), value.as_slice())
+                })
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        match self.skip_removed() {
+            Source::Finished => None,
+            Source::Base => self.base.peek(),
+            Source::Changes | Source::Both => self.changes.peek().map(|&(key, value)| {
+                let value = value.as_ref().expect("Removed key");
+                (key.as_slice(), value.as_slice())
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum::storage::{Database, MemoryDB};
+
+    use super::*;
+
+    const NAME: &str = "map";
+
+    fn entries(snapshot: &Snapshot, from: &[u8]) -> Vec<(Key, Value)> {
+        let mut iter = snapshot.iter(NAME, from);
+        let mut entries = Vec::new();
+        while let Some((key, value)) = iter.next() {
+            entries.push((key.to_vec(), value.to_vec()));
+        }
+        entries
+    }
+
+    #[test]
+    fn frozen_fork_keeps_state() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        fork.put(NAME, vec![1], vec![10]);
+        fork.put(NAME, vec![2], vec![20]);
+        fork.put(NAME, vec![4], vec![40]);
+        db.merge(fork.into_patch()).unwrap();
+
+        let base: Arc<Snapshot> = Arc::from(db.snapshot());
+        let mut fork = db.fork();
+        fork.put(NAME, vec![2], vec![21]);
+        fork.put(NAME, vec![3], vec![30]);
+        fork.remove(NAME, vec![4]);
+        let frozen = FrozenFork::new(base, fork.patch());
+        fork.put(NAME, vec![5], vec![50]);
+        fork.remove(NAME, vec![1]);
+
+        assert_eq!(Some(vec![10]), frozen.get(NAME, &[1]));
+        assert_eq!(Some(vec![21]), frozen.get(NAME, &[2]));
+        assert_eq!(None, frozen.get(NAME, &[4]));
+        assert_eq!(None, frozen.get(NAME, &[5]));
+        assert_eq!(
+            vec![
+                (vec![1], vec![10]),
+                (vec![2], vec![21]),
+                (vec![3], vec![30])
+            ],
+            entries(&frozen, &[])
+        );
+        assert_eq!(vec![(vec![3], vec![30])], entries(&frozen, &[3]));
+    }
+
+    #[test]
+    fn frozen_fork_peek() {
+        let db = MemoryDB::new();
+        let base: Arc<Snapshot> = Arc::from(db.snapshot());
+        let mut fork = db.fork();
+        fork.put(NAME, vec![1], vec![10]);
+        fork.remove(NAME, vec![0]);
+        let frozen = FrozenFork::new(base, fork.patch());
+
+        let mut iter = frozen.iter(NAME, &[]);
+        assert_eq!(Some((&[1][..], &[10][..])), iter.peek());
+        assert_eq!(Some((&[1][..], &[10][..])), iter.next());
+        assert_eq!(None, iter.peek());
+    }
+}
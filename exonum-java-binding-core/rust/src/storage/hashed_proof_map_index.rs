@@ -0,0 +1,396 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A proof map with keys of arbitrary length.
+//!
+//! `ProofMapIndex` requires keys of exactly `PROOF_MAP_KEY_SIZE` bytes, so each key is hashed
+//! with `exonum::crypto::hash` (SHA-256) and the values are stored in a `ProofMapIndex`
+//! with the name of the hashed map under the hashes of their keys. A sibling index keeps
+//! the original keys so that iteration can recover them:
+//! - `<name>.keys` — a `MapIndex` from the hash of each key to the key itself.
+//!
+//! The root hash and the proofs of a hashed map are over the **hashed** keys: a proof
+//! for `key` contains `hash(key)`, and Java must verify it against `hash(key)`, not `key`.
+//! The proofs of the maps created with `ProofMapIndexProxy.nativeCreateRaw` are over
+//! the raw keys.
+//!
+//! All the modifications must be made through `HashedProofMapIndexProxy` to keep
+//! the indices consistent.
+
+use exonum::crypto;
+use exonum::storage::proof_map_index::{MapProof, PROOF_MAP_KEY_SIZE};
+use exonum::storage::{Fork, MapIndex, ProofMapIndex, Snapshot};
+use jni::objects::{JClass, JObject, JString};
+use jni::sys::{jboolean, jbyteArray, jobject};
+use jni::JNIEnv;
+
+use std::panic;
+use std::ptr;
+use std::vec;
+
+use storage::db::{Key, Value, View, ViewRef};
+use storage::proof_map_index::convert_to_java_proof;
+use utils::{self, Handle, PairIter};
+
+type HashedKey = [u8; PROOF_MAP_KEY_SIZE];
+
+struct HashedMap<T> {
+    name: String,
+    view: T,
+}
+
+enum IndexType {
+    SnapshotIndex(HashedMap<&'static Snapshot>),
+    ForkIndex(HashedMap<&'static mut Fork>),
+}
+
+type Iter = PairIter<vec::IntoIter<(Key, Value)>>;
+
+const JAVA_ENTRY_FQN: &str = "com/exonum/binding/storage/indices/MapEntryInternal";
+
+/// Returns the key under which the value for `key` is stored in the proof map.
+fn hash_key(key: &[u8]) -> HashedKey {
+    let mut hashed_key = HashedKey::default();
+    hashed_key.copy_from_slice(crypto::hash(key).as_ref());
+    hashed_key
+}
+
+impl<T> HashedMap<T> {
+    fn keys_name(&self) -> String {
+        format!("{}.keys", self.name)
+    }
+}
+
+impl<T: AsRef<Snapshot>> HashedMap<T> {
+    fn values(&self) -> ProofMapIndex<&Snapshot, HashedKey, Value> {
+        ProofMapIndex::new(self.name.clone(), self.view.as_ref())
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Value> {
+        self.values().get(&hash_key(key))
+    }
+
+    fn contains(&self, key: &[u8]) -> bool {
+        self.values().contains(&hash_key(key))
+    }
+
+    fn merkle_root(&self) -> crypto::Hash {
+        self.values().merkle_root()
+    }
+
+    fn get_proof(&self, key: &[u8]) -> MapProof<HashedKey, Value> {
+        self.values().get_proof(hash_key(key))
+    }
+
+    /// Returns the entries with the original keys in the order of their hashes.
+    fn entries(&self) -> Vec<(Key, Value)> {
+        let snapshot: &Snapshot = self.view.as_ref();
+        let keys: MapIndex<_, HashedKey, Key> = MapIndex::new(self.keys_name(), snapshot);
+        self.values()
+            .iter()
+            .map(|(hashed_key, value)| {
+                let key = keys.get(&hashed_key).expect("Inconsistent hashed map");
+                (key, value)
+            }).collect()
+    }
+}
+
+impl<'a> HashedMap<&'a mut Fork> {
+    fn put(&mut self, key: &[u8], value: Value) {
+        let hashed_key = hash_key(key);
+        MapIndex::new(self.keys_name(), &mut *self.view).put(&hashed_key, key.to_vec());
+        ProofMapIndex::new(self.name.clone(), &mut *self.view).put(&hashed_key, value);
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        let hashed_key = hash_key(key);
+        let mut keys: MapIndex<_, HashedKey, Key> =
+            MapIndex::new(self.keys_name(), &mut *self.view);
+        keys.remove(&hashed_key);
+        let mut values: ProofMapIndex<_, HashedKey, Value> =
+            ProofMapIndex::new(self.name.clone(), &mut *self.view);
+        values.remove(&hashed_key);
+    }
+}
+
+/// Returns a pointer to the created hashed proof map object.
+///
+/// The keys may be of any length; the root hash and the proofs are over the hashes
+/// of the keys.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_HashedProofMapIndexProxy_nativeCreateHashed(
+    env: JNIEnv,
+    _: JClass,
+    name: JString,
+    view_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let name = utils::convert_to_string(&env, name)?;
+        Ok(utils::to_handle(
+            match *utils::cast_handle::<View>(view_handle).get() {
+                ViewRef::Snapshot(snapshot) => IndexType::SnapshotIndex(HashedMap {
+                    name,
+                    view: snapshot,
+                }),
+                ViewRef::Fork(ref mut fork) => IndexType::ForkIndex(HashedMap { name, view: fork }),
+            },
+        ))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Destroys the underlying hashed proof map object and frees memory.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_HashedProofMapIndexProxy_nativeFree(
+    env: JNIEnv,
+    _: JClass,
+    map_handle: Handle,
+) {
+    utils::drop_handle::<IndexType>(&env, map_handle);
+}
+
+/// Returns the root hash of the proof map over the hashed keys or default hash value
+/// if it is empty.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_HashedProofMapIndexProxy_nativeGetRootHash(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let hash = match *utils::cast_handle::<IndexType>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.merkle_root(),
+            IndexType::ForkIndex(ref map) => map.merkle_root(),
+        };
+        utils::convert_hash(&env, &hash)
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns value identified by the `key`. Null pointer is returned if value is not found.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_HashedProofMapIndexProxy_nativeGet(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let key = env.convert_byte_array(key)?;
+        let val = match *utils::cast_handle::<IndexType>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.get(&key),
+            IndexType::ForkIndex(ref map) => map.get(&key),
+        };
+        match val {
+            Some(val) => env.byte_array_from_slice(&val),
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns `true` if the map contains a value for the specified key.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_HashedProofMapIndexProxy_nativeContainsKey(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+) -> jboolean {
+    let res = panic::catch_unwind(|| {
+        let key = env.convert_byte_array(key)?;
+        Ok(match *utils::cast_handle::<IndexType>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.contains(&key),
+            IndexType::ForkIndex(ref map) => map.contains(&key),
+        } as jboolean)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns Java-proof object for the `key`.
+///
+/// The proof is over `hash(key)`: its entries and missing keys contain the hash of the key,
+/// so it must be verified against `hash(key)`, not the `key` itself.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_HashedProofMapIndexProxy_nativeGetProof(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+) -> jobject {
+    let res = panic::catch_unwind(|| {
+        let key = env.convert_byte_array(key)?;
+        let proof = match *utils::cast_handle::<IndexType>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.get_proof(&key),
+            IndexType::ForkIndex(ref map) => map.get_proof(&key),
+        };
+
+        Ok(convert_to_java_proof(&env, proof)?.into_inner())
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Sets `value` identified by the `key` into the map, remembering the original key.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_HashedProofMapIndexProxy_nativePut(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+    value: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| match *utils::cast_handle::<IndexType>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map) => {
+            let key = env.convert_byte_array(key)?;
+            let value = env.convert_byte_array(value)?;
+            map.put(&key, value);
+            Ok(())
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Removes value identified by the `key` from the map along with the original key.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_HashedProofMapIndexProxy_nativeRemove(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| match *utils::cast_handle::<IndexType>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map) => {
+            let key = env.convert_byte_array(key)?;
+            map.remove(&key);
+            Ok(())
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the pointer to the iterator over the map entries with the original keys,
+/// in the order of the key hashes.
+///
+/// The entries are collected when the iterator is created, so the iterator is not affected
+/// by the subsequent modifications of the map.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_HashedProofMapIndexProxy_nativeCreateEntriesIter(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let entries = match *utils::cast_handle::<IndexType>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.entries(),
+            IndexType::ForkIndex(ref map) => map.entries(),
+        };
+        let iter = Iter::new(&env, entries.into_iter(), JAVA_ENTRY_FQN)?;
+        Ok(utils::to_handle(iter))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the next value from the iterator. Returns null pointer when iteration is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_HashedProofMapIndexProxy_nativeEntriesIterNext(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) -> jobject {
+    let res = panic::catch_unwind(|| {
+        let iterWrapper = utils::cast_handle::<Iter>(iter_handle);
+        match iterWrapper.iter.next() {
+            Some(val) => {
+                let key: JObject = env.byte_array_from_slice(&val.0)?.into();
+                let value: JObject = env.byte_array_from_slice(&val.1)?.into();
+                Ok(env
+                    .new_object_by_id(
+                        &iterWrapper.element_class,
+                        iterWrapper.constructor_id,
+                        &[key.into(), value.into()],
+                    )?.into_inner())
+            }
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Destroys the underlying iterator object and frees memory.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_HashedProofMapIndexProxy_nativeEntriesIterFree(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) {
+    utils::drop_handle::<Iter>(&env, iter_handle);
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum::storage::{Database, MemoryDB};
+
+    use super::*;
+
+    #[test]
+    fn put_get_remove() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        {
+            let mut map = HashedMap {
+                name: "test".to_owned(),
+                view: &mut fork,
+            };
+            map.put(&[1], vec![10]);
+            map.put(&[2, 2, 2], vec![20]);
+            map.put(&[3; 40], vec![30]);
+            map.remove(&[2, 2, 2]);
+
+            assert_eq!(Some(vec![10]), map.get(&[1]));
+            assert!(map.contains(&[3; 40]));
+            assert!(!map.contains(&[2, 2, 2]));
+
+            let mut entries = map.entries();
+            entries.sort();
+            assert_eq!(vec![(vec![1], vec![10]), (vec![3; 40], vec![30])], entries);
+        }
+        let keys: MapIndex<_, HashedKey, Key> = MapIndex::new("test.keys", &fork);
+        assert_eq!(Some(vec![1]), keys.get(&hash_key(&[1])));
+        assert_eq!(None, keys.get(&hash_key(&[2, 2, 2])));
+    }
+
+    #[test]
+    fn proof_is_over_hashed_key() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        let mut map = HashedMap {
+            name: "test".to_owned(),
+            view: &mut fork,
+        };
+        map.put(&[1, 2, 3], vec![42]);
+
+        let checked_proof = map.get_proof(&[1, 2, 3]).check().unwrap();
+        assert_eq!(map.merkle_root(), checked_proof.merkle_root());
+        assert_eq!(
+            vec![(&hash_key(&[1, 2, 3]), &vec![42])],
+            checked_proof.entries()
+        );
+    }
+}
@@ -15,9 +15,10 @@
 use exonum::storage::key_set_index::KeySetIndexIter;
 use exonum::storage::{Fork, KeySetIndex, Snapshot};
 use jni::objects::{JClass, JObject, JString};
-use jni::sys::{jboolean, jbyteArray};
+use jni::sys::{jboolean, jbyteArray, jlong, jobjectArray};
 use jni::JNIEnv;
 
+use std::cmp::Ordering;
 use std::panic;
 use std::ptr;
 
@@ -54,6 +55,10 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_KeySetIndexProxy_
 }
 
 /// Returns a pointer to the created `KeySetIndex` object in an index family (= group).
+///
+/// The id is composed with `utils::compose_family_id` as a single part, so the index is the same
+/// as the one created with `nativeCreateInGroupMulti(group_name, [set_id])`, and different ids
+/// never collide with each other or with the multi-part ids.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_KeySetIndexProxy_nativeCreateInGroup(
     env: JNIEnv,
@@ -65,6 +70,33 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_KeySetIndexProxy_
     let res = panic::catch_unwind(|| {
         let group_name = utils::convert_to_string(&env, group_name)?;
         let set_id = env.convert_byte_array(set_id)?;
+        let set_id = utils::compose_family_id(&[set_id.as_slice()]);
+        let view_ref = utils::cast_handle::<View>(view_handle).get();
+        Ok(utils::to_handle(match *view_ref {
+            ViewRef::Snapshot(snapshot) => {
+                IndexType::SnapshotIndex(Index::new_in_family(group_name, &set_id, &*snapshot))
+            }
+            ViewRef::Fork(ref mut fork) => {
+                IndexType::ForkIndex(Index::new_in_family(group_name, &set_id, fork))
+            }
+        }))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the created `KeySetIndex` object in an index family (= group)
+/// with the id composed of several parts with `utils::compose_family_id`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_KeySetIndexProxy_nativeCreateInGroupMulti(
+    env: JNIEnv,
+    _: JClass,
+    group_name: JString,
+    id_parts: jobjectArray,
+    view_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let group_name = utils::convert_to_string(&env, group_name)?;
+        let set_id = utils::convert_to_family_id(&env, id_parts)?;
         let view_ref = utils::cast_handle::<View>(view_handle).get();
         Ok(utils::to_handle(match *view_ref {
             ViewRef::Snapshot(snapshot) => {
@@ -144,6 +176,36 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_KeySetIndexProxy_
     utils::unwrap_exc_or_default(&env, res)
 }
 
+/// Returns the number of values present in the first set but not in the second one.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_KeySetIndexProxy_nativeDifferenceCount(
+    env: JNIEnv,
+    _: JObject,
+    set_a_handle: Handle,
+    set_b_handle: Handle,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        let counts = compare_sets(set_a_handle, set_b_handle);
+        Ok(counts.only_first as jlong)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the number of values present in both sets.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_KeySetIndexProxy_nativeIntersectionCount(
+    env: JNIEnv,
+    _: JObject,
+    set_a_handle: Handle,
+    set_b_handle: Handle,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        let counts = compare_sets(set_a_handle, set_b_handle);
+        Ok(counts.both as jlong)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
 /// Inserts value in the set.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_KeySetIndexProxy_nativeAdd(
@@ -231,3 +293,92 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_KeySetIndexProxy_
 ) {
     utils::drop_handle::<KeySetIndexIter<Key>>(&env, iter_handle);
 }
+
+/// The numbers of values found by a merge walk over two sets.
+#[derive(Debug, Default, PartialEq)]
+struct SetCounts {
+    only_first: u64,
+    both: u64,
+}
+
+fn compare_sets(first_handle: Handle, second_handle: Handle) -> SetCounts {
+    let first = iter_set(utils::cast_handle::<IndexType>(first_handle));
+    let second = iter_set(utils::cast_handle::<IndexType>(second_handle));
+    count_common(first, second)
+}
+
+fn iter_set(set: &IndexType) -> KeySetIndexIter<Key> {
+    match *set {
+        IndexType::SnapshotIndex(ref set) => set.iter(),
+        IndexType::ForkIndex(ref set) => set.iter(),
+    }
+}
+
+/// Walks two sorted streams of values at once, counting the values present only in the first
+/// stream and in both of them.
+fn count_common<A, B>(first: A, second: B) -> SetCounts
+where
+    A: Iterator<Item = Key>,
+    B: Iterator<Item = Key>,
+{
+    let mut counts = SetCounts::default();
+    let mut second = second.peekable();
+    for value in first {
+        loop {
+            let ordering = match second.peek() {
+                Some(other) => other.cmp(&value),
+                None => Ordering::Greater,
+            };
+            match ordering {
+                Ordering::Less => {
+                    second.next();
+                }
+                Ordering::Equal => {
+                    second.next();
+                    counts.both += 1;
+                    break;
+                }
+                Ordering::Greater => {
+                    counts.only_first += 1;
+                    break;
+                }
+            }
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(first: &[u8], second: &[u8]) -> SetCounts {
+        count_common(
+            first.iter().map(|&v| vec![v]),
+            second.iter().map(|&v| vec![v]),
+        )
+    }
+
+    #[test]
+    fn count_common_values() {
+        assert_eq!(
+            SetCounts {
+                only_first: 2,
+                both: 2,
+            },
+            count(&[1, 2, 4, 6], &[0, 2, 3, 6, 7])
+        );
+    }
+
+    #[test]
+    fn count_common_empty_sets() {
+        assert_eq!(SetCounts::default(), count(&[], &[1, 2]));
+        assert_eq!(
+            SetCounts {
+                only_first: 2,
+                both: 0,
+            },
+            count(&[1, 2], &[])
+        );
+    }
+}
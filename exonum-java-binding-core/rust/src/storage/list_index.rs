@@ -15,7 +15,7 @@
 use exonum::storage::list_index::ListIndexIter;
 use exonum::storage::{Fork, ListIndex, Snapshot};
 use jni::objects::{JClass, JObject, JString};
-use jni::sys::{jboolean, jbyteArray, jlong};
+use jni::sys::{jboolean, jbyteArray, jlong, jobjectArray};
 use jni::JNIEnv;
 
 use std::panic;
@@ -54,6 +54,10 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_na
 }
 
 /// Returns a pointer to the created `ListIndex` instance in an index family (= group).
+///
+/// The id is composed with `utils::compose_family_id` as a single part, so the index is the same
+/// as the one created with `nativeCreateInGroupMulti(group_name, [list_id])`, and different ids
+/// never collide with each other or with the multi-part ids.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeCreateInGroup(
     env: JNIEnv,
@@ -65,6 +69,33 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_na
     let res = panic::catch_unwind(|| {
         let group_name = utils::convert_to_string(&env, group_name)?;
         let list_id = env.convert_byte_array(list_id)?;
+        let list_id = utils::compose_family_id(&[list_id.as_slice()]);
+        let view_ref = utils::cast_handle::<View>(view_handle).get();
+        Ok(utils::to_handle(match *view_ref {
+            ViewRef::Snapshot(snapshot) => {
+                IndexType::SnapshotIndex(Index::new_in_family(group_name, &list_id, &*snapshot))
+            }
+            ViewRef::Fork(ref mut fork) => {
+                IndexType::ForkIndex(Index::new_in_family(group_name, &list_id, fork))
+            }
+        }))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the created `ListIndex` instance in an index family (= group)
+/// with the id composed of several parts with `utils::compose_family_id`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeCreateInGroupMulti(
+    env: JNIEnv,
+    _: JClass,
+    group_name: JString,
+    id_parts: jobjectArray,
+    view_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let group_name = utils::convert_to_string(&env, group_name)?;
+        let list_id = utils::convert_to_family_id(&env, id_parts)?;
         let view_ref = utils::cast_handle::<View>(view_handle).get();
         Ok(utils::to_handle(match *view_ref {
             ViewRef::Snapshot(snapshot) => {
@@ -88,7 +119,7 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_na
     utils::drop_handle::<IndexType>(&env, list_handle);
 }
 
-/// Returns the value by index. Null pointer is returned if value is not found.
+/// Returns the value by index. Throws `IndexOutOfBoundsException` if the index is out of bounds.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeGet(
     env: JNIEnv,
@@ -97,13 +128,14 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_na
     index: jlong,
 ) -> jbyteArray {
     let res = panic::catch_unwind(|| {
-        let val = match *utils::cast_handle::<IndexType>(list_handle) {
-            IndexType::SnapshotIndex(ref list) => list.get(index as u64),
-            IndexType::ForkIndex(ref list) => list.get(index as u64),
+        let index = utils::convert_to_index(&env, index)?;
+        let (val, len) = match *utils::cast_handle::<IndexType>(list_handle) {
+            IndexType::SnapshotIndex(ref list) => (list.get(index), list.len()),
+            IndexType::ForkIndex(ref list) => (list.get(index), list.len()),
         };
         match val {
             Some(val) => env.byte_array_from_slice(&val),
-            None => Ok(ptr::null_mut()),
+            None => utils::check_index_in_bounds(&env, index, len).map(|_| ptr::null_mut()),
         }
     });
     utils::unwrap_exc_or(&env, res, ptr::null_mut())
@@ -180,6 +212,7 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_na
 }
 
 /// Returns pointer to the iterator over list starting at given index.
+/// Throws `IndexOutOfBoundsException` if the index is negative.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeIterFrom(
     env: JNIEnv,
@@ -188,10 +221,11 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_na
     index_from: jlong,
 ) -> Handle {
     let res = panic::catch_unwind(|| {
+        let index_from = utils::convert_to_index(&env, index_from)?;
         Ok(utils::to_handle(
             match *utils::cast_handle::<IndexType>(list_handle) {
-                IndexType::SnapshotIndex(ref list) => list.iter_from(index_from as u64),
-                IndexType::ForkIndex(ref list) => list.iter_from(index_from as u64),
+                IndexType::SnapshotIndex(ref list) => list.iter_from(index_from),
+                IndexType::ForkIndex(ref list) => list.iter_from(index_from),
             },
         ))
     });
@@ -219,6 +253,27 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_na
     utils::unwrap_exc_or_default(&env, res)
 }
 
+/// Appends the values to the list in order and returns the new length of the list.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeExtend(
+    env: JNIEnv,
+    _: JObject,
+    list_handle: Handle,
+    values: jobjectArray,
+) -> jlong {
+    let res = panic::catch_unwind(|| match *utils::cast_handle::<IndexType>(list_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut list) => {
+            let values = utils::convert_to_byte_arrays(&env, values)?;
+            list.extend(values);
+            Ok(list.len() as jlong)
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
 /// Removes the last element from a list and returns it, or null pointer if it is empty.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeRemoveLast(
@@ -240,6 +295,7 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_na
 }
 
 /// Shortens the list, keeping the first len elements and dropping the rest.
+/// Throws `IndexOutOfBoundsException` if `len` is negative.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeTruncate(
     env: JNIEnv,
@@ -252,14 +308,16 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_na
             panic!("Unable to modify snapshot.");
         }
         IndexType::ForkIndex(ref mut list) => {
-            list.truncate(len as u64);
+            let len = utils::convert_to_index(&env, len)?;
+            list.truncate(len);
             Ok(())
         }
     });
     utils::unwrap_exc_or_default(&env, res)
 }
 
-/// Sets value into specified index. Panics if `i` is out of bounds.
+/// Sets value into specified index. Throws `IndexOutOfBoundsException` if the index
+/// is out of bounds.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_nativeSet(
     env: JNIEnv,
@@ -273,8 +331,10 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ListIndexProxy_na
             panic!("Unable to modify snapshot.");
         }
         IndexType::ForkIndex(ref mut list) => {
+            let index = utils::convert_to_index(&env, index)?;
+            utils::check_index_in_bounds(&env, index, list.len())?;
             let value = env.convert_byte_array(value)?;
-            list.set(index as u64, value);
+            list.set(index, value);
             Ok(())
         }
     });
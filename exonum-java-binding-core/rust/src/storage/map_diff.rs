@@ -0,0 +1,274 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use exonum::storage::{Iter as StorageIter, Snapshot};
+use jni::objects::{JObject, JString, JValue};
+use jni::sys::{jint, jobject};
+use jni::JNIEnv;
+
+use std::cmp::Ordering;
+use std::panic;
+use std::ptr;
+
+use storage::db::{Guarded, Key, Value, View, ViewGuard, ViewRef};
+use utils::{self, Handle};
+use JniResult;
+
+const JAVA_DIFF_ENTRY_FQN: &str = "com/exonum/binding/storage/indices/MapDiffEntryInternal";
+const JAVA_DIFF_ENTRY_SIG: &str = "(I[B[B[B)V";
+
+// The iterator handle keeps both views alive, so the iterator remains valid even if
+// the views are destroyed before it.
+type Iter = Guarded<Guarded<DiffIter<'static>>>;
+
+const CHANGE_ADDED: jint = 0;
+const CHANGE_REMOVED: jint = 1;
+const CHANGE_MODIFIED: jint = 2;
+
+/// A difference of the entries with the same key.
+#[derive(Debug, PartialEq)]
+struct DiffEntry {
+    change: jint,
+    key: Key,
+    old_value: Option<Value>,
+    new_value: Option<Value>,
+}
+
+/// An iterator over the differences between the `MapIndex`es with the same name in two views,
+/// in the order of keys. It merge-walks the sorted entries of both indices, so neither of them
+/// is materialized.
+struct DiffIter<'a> {
+    old: StorageIter<'a>,
+    new: StorageIter<'a>,
+    next_old: Option<(Key, Value)>,
+    next_new: Option<(Key, Value)>,
+}
+
+impl<'a> DiffIter<'a> {
+    fn new(old: &'a Snapshot, new: &'a Snapshot, name: &str) -> Self {
+        let mut old = old.iter(name, &[]);
+        let mut new = new.iter(name, &[]);
+        let next_old = next_entry(&mut old);
+        let next_new = next_entry(&mut new);
+        DiffIter {
+            old,
+            new,
+            next_old,
+            next_new,
+        }
+    }
+}
+
+impl<'a> Iterator for DiffIter<'a> {
+    type Item = DiffEntry;
+
+    fn next(&mut self) -> Option<DiffEntry> {
+        loop {
+            match (self.next_old.take(), self.next_new.take()) {
+                (None, None) => return None,
+                (Some((key, value)), None) => {
+                    self.next_old = next_entry(&mut self.old);
+                    return Some(removed(key, value));
+                }
+                (None, Some((key, value))) => {
+                    self.next_new = next_entry(&mut self.new);
+                    return Some(added(key, value));
+                }
+                (Some(old), Some(new)) => match old.0.cmp(&new.0) {
+                    Ordering::Less => {
+                        self.next_old = next_entry(&mut self.old);
+                        self.next_new = Some(new);
+                        return Some(removed(old.0, old.1));
+                    }
+                    Ordering::Greater => {
+                        self.next_old = Some(old);
+                        self.next_new = next_entry(&mut self.new);
+                        return Some(added(new.0, new.1));
+                    }
+                    Ordering::Equal => {
+                        self.next_old = next_entry(&mut self.old);
+                        self.next_new = next_entry(&mut self.new);
+                        if old.1 != new.1 {
+                            return Some(DiffEntry {
+                                change: CHANGE_MODIFIED,
+                                key: old.0,
+                                old_value: Some(old.1),
+                                new_value: Some(new.1),
+                            });
+                        }
+                    }
+                },
+            }
+        }
+    }
+}
+
+fn next_entry(iter: &mut StorageIter) -> Option<(Key, Value)> {
+    iter.next()
+        .map(|(key, value)| (key.to_vec(), value.to_vec()))
+}
+
+fn added(key: Key, value: Value) -> DiffEntry {
+    DiffEntry {
+        change: CHANGE_ADDED,
+        key,
+        old_value: None,
+        new_value: Some(value),
+    }
+}
+
+fn removed(key: Key, value: Value) -> DiffEntry {
+    DiffEntry {
+        change: CHANGE_REMOVED,
+        key,
+        old_value: Some(value),
+        new_value: None,
+    }
+}
+
+/// Returns the snapshot of the view along with the guard keeping it alive.
+fn as_snapshot(view_handle: Handle) -> (&'static Snapshot, ViewGuard) {
+    let view = utils::cast_handle::<View>(view_handle);
+    let guard = view.guard();
+    let snapshot: &'static Snapshot = match *view.get() {
+        ViewRef::Snapshot(snapshot) => snapshot,
+        ViewRef::Fork(ref fork) => &**fork,
+    };
+    (snapshot, guard)
+}
+
+/// Returns a pointer to the iterator over the differences between the `MapIndex`es with
+/// the given name in two views: the entries that were added, removed or modified in
+/// the second view compared to the first one.
+///
+/// Each difference is returned as `MapDiffEntryInternal` with the type of the change
+/// (`0` — added, `1` — removed, `2` — modified), the key, the old and the new value,
+/// one of which is null for the added and the removed entries.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeDiff(
+    env: JNIEnv,
+    _: JObject,
+    snapshot_a_handle: Handle,
+    snapshot_b_handle: Handle,
+    name: JString,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let name = utils::convert_to_string(&env, name)?;
+        let (old, old_guard) = as_snapshot(snapshot_a_handle);
+        let (new, new_guard) = as_snapshot(snapshot_b_handle);
+        let iter = DiffIter::new(old, new, &name);
+        Ok(utils::to_handle(Guarded::new(
+            Guarded::new(iter, old_guard),
+            new_guard,
+        )))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the next difference from the iterator. Returns null pointer when iteration
+/// is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeDiffIterNext(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) -> jobject {
+    let res = panic::catch_unwind(|| {
+        let iter = utils::cast_handle::<Iter>(iter_handle);
+        match iter.next() {
+            Some(entry) => {
+                let key: JObject = utils::convert_bytes(&env, &entry.key)?.into();
+                let old_value = to_java_value(&env, entry.old_value)?;
+                let new_value = to_java_value(&env, entry.new_value)?;
+                Ok(env
+                    .new_object(
+                        JAVA_DIFF_ENTRY_FQN,
+                        JAVA_DIFF_ENTRY_SIG,
+                        &[
+                            JValue::Int(entry.change),
+                            key.into(),
+                            old_value.into(),
+                            new_value.into(),
+                        ],
+                    )?.into_inner())
+            }
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Destroys the underlying differences iterator object and frees memory.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeDiffIterFree(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) {
+    utils::drop_handle::<Iter>(&env, iter_handle);
+}
+
+fn to_java_value<'a>(env: &'a JNIEnv, value: Option<Value>) -> JniResult<JObject<'a>> {
+    match value {
+        Some(value) => Ok(utils::convert_bytes(env, &value)?.into()),
+        None => Ok(JObject::null()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum::storage::{Database, MapIndex, MemoryDB};
+
+    use super::*;
+
+    #[test]
+    fn diff() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        {
+            let mut map: MapIndex<_, Key, Value> = MapIndex::new("test", &mut fork);
+            map.put(&vec![1], vec![10]);
+            map.put(&vec![2], vec![20]);
+            map.put(&vec![3], vec![30]);
+        }
+        db.merge(fork.into_patch()).unwrap();
+        let old = db.snapshot();
+
+        let mut fork = db.fork();
+        {
+            let mut map: MapIndex<_, Key, Value> = MapIndex::new("test", &mut fork);
+            map.remove(&vec![1]);
+            map.put(&vec![2], vec![21]);
+            map.put(&vec![4], vec![40]);
+        }
+        db.merge(fork.into_patch()).unwrap();
+        let new = db.snapshot();
+
+        let diff: Vec<_> = DiffIter::new(&*old, &*new, "test").collect();
+        assert_eq!(
+            vec![
+                removed(vec![1], vec![10]),
+                DiffEntry {
+                    change: CHANGE_MODIFIED,
+                    key: vec![2],
+                    old_value: Some(vec![20]),
+                    new_value: Some(vec![21]),
+                },
+                added(vec![4], vec![40]),
+            ],
+            diff
+        );
+        assert_eq!(0, DiffIter::new(&*new, &*new, "test").count());
+    }
+}
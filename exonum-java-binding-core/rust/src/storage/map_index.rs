@@ -12,28 +12,235 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use exonum::storage::map_index::{MapIndexIter, MapIndexKeys, MapIndexValues};
+use exonum::crypto::{self, Hash};
+use exonum::storage::map_index::{MapIndexIter, MapIndexKeys};
 use exonum::storage::{Fork, MapIndex, Snapshot};
-use jni::objects::{JClass, JObject, JString};
-use jni::sys::{jboolean, jbyteArray, jobject};
+use jni::objects::{GlobalRef, JByteBuffer, JClass, JMethodID, JObject, JString};
+use jni::sys::{
+    jboolean, jbyteArray, jint, jlong, jlongArray, jobject, jobjectArray, jsize, jstring, JNI_TRUE,
+};
 use jni::JNIEnv;
+use serde_json;
 
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 use std::panic;
 use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
 
-use storage::db::{Key, Value, View, ViewRef};
-use utils::{self, Handle, PairIter};
+use storage::db::{self, Guarded, Key, Value, View, ViewRef};
+use utils::{
+    self, Handle, Operation, PairIter, ARITHMETIC_EXCEPTION, ILLEGAL_ARGUMENT_EXCEPTION,
+    INDEX_OUT_OF_BOUNDS_EXCEPTION, IO_EXCEPTION, NO_SUCH_ELEMENT_EXCEPTION, NULL_POINTER_EXCEPTION,
+};
+use JniResult;
 
 type Index<T> = MapIndex<T, Key, Value>;
 
 enum IndexType {
     SnapshotIndex(Index<&'static Snapshot>),
-    ForkIndex(Index<&'static mut Fork>),
+    /// A fork index with the cached number of its entries, see `nativePutReturningSize`.
+    ForkIndex(Index<&'static mut Fork>, CachedSize),
 }
 
-type Iter<'a> = PairIter<MapIndexIter<'a, Key, Value>>;
+/// The cached number of entries of a fork index, which is discarded once the fork
+/// is rolled back or replays operations, see `db::fork_generation`.
+#[derive(Default)]
+struct CachedSize {
+    count: Option<u64>,
+    generation: usize,
+}
+
+impl CachedSize {
+    /// Returns the cached number of entries, unless it is not counted yet or is stale.
+    fn get(&self) -> Option<u64> {
+        if self.generation == db::fork_generation() {
+            self.count
+        } else {
+            None
+        }
+    }
+
+    /// Returns the cached number of entries for adjustment, discarding it if it is stale.
+    fn count(&mut self) -> Option<&mut u64> {
+        if self.generation != db::fork_generation() {
+            self.count = None;
+        }
+        self.count.as_mut()
+    }
+
+    fn set(&mut self, count: u64) {
+        self.count = Some(count);
+        self.generation = db::fork_generation();
+    }
+
+    fn invalidate(&mut self) {
+        self.count = None;
+    }
+}
+
+/// A `MapIndex` handle, which keeps the view of the index alive.
+type IndexHandle = Guarded<IndexType>;
+
+// The iterator handles keep the view of the index alive, so the iterators remain valid
+// even if the index or the view is destroyed before them.
+type Iter<'a> = Guarded<Entries<'a>>;
+// The values iterators are boxed, as they are either the iterators of the map
+// or the buffered reverse ones.
+type Keys<'a> = Guarded<KeysIter<'a>>;
+type Values<'a> = Guarded<Box<Iterator<Item = Value> + 'a>>;
+type ValuesInRange<'a> = Guarded<ValuesBetween<'a>>;
+type FilteredKeys<'a> = Guarded<KeysFilter<'a>>;
+
+/// An iterator over the entries, which remembers the key of the last returned entry,
+/// see `nativeIterCursor`.
+struct Entries<'a> {
+    pairs: PairIter<MapIndexIter<'a, Key, Value>>,
+    last_key: Option<Key>,
+}
+
+impl<'a> Entries<'a> {
+    fn new(pairs: PairIter<MapIndexIter<'a, Key, Value>>) -> Self {
+        Entries {
+            pairs,
+            last_key: None,
+        }
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (Key, Value);
+
+    fn next(&mut self) -> Option<(Key, Value)> {
+        let entry = self.pairs.iter.next();
+        if let Some((ref key, _)) = entry {
+            self.last_key = Some(key.clone());
+        }
+        entry
+    }
+}
+
+/// An iterator over the keys, which remembers how it was created, see `nativeKeysIterReset`.
+struct KeysIter<'a> {
+    // Either the iterator of the map or the buffered reverse one.
+    keys: Box<Iterator<Item = Key> + 'a>,
+    origin: KeysOrigin,
+}
+
+/// The parameters a keys-iterator is created with.
+#[derive(Clone)]
+struct KeysOrigin {
+    /// The handle of the map the iterator is created from.
+    map_handle: Handle,
+    /// The key the iteration starts at, or `None` to start at the first key of the map.
+    from: Option<Key>,
+    /// Whether the keys are iterated in the descending order.
+    reverse: bool,
+}
+
+impl<'a> Iterator for KeysIter<'a> {
+    type Item = Key;
+
+    fn next(&mut self) -> Option<Key> {
+        self.keys.next()
+    }
+}
+
+/// An iterator over the values of the keys in a range, see `nativeValuesBetween`.
+///
+/// `MapIndexValues` does not expose the keys, so the iterator walks over the entries
+/// to find the end of the range and yields their values.
+struct ValuesBetween<'a> {
+    entries: MapIndexIter<'a, Key, Value>,
+    to: Key,
+}
+
+impl<'a> Iterator for ValuesBetween<'a> {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        match self.entries.next() {
+            Some((ref key, _)) if *key >= self.to => None,
+            Some((_, value)) => Some(value),
+            None => None,
+        }
+    }
+}
+
+/// An iterator over the keys accepted by a Java predicate, see `nativeFilterKeys`.
+struct KeysFilter<'a> {
+    keys: MapIndexKeys<'a, Key>,
+    predicate: GlobalRef,
+    // Set once the predicate throws, so the iteration is not resumed after an exception.
+    aborted: bool,
+}
+
+impl<'a> KeysFilter<'a> {
+    /// Returns the next key accepted by the predicate.
+    fn next_key(&mut self, env: &JNIEnv) -> JniResult<Option<Key>> {
+        if self.aborted {
+            return Ok(None);
+        }
+        match self.find_next(env) {
+            Err(e) => {
+                self.aborted = true;
+                Err(e)
+            }
+            result => result,
+        }
+    }
+
+    fn find_next(&mut self, env: &JNIEnv) -> JniResult<Option<Key>> {
+        while let Some(key) = self.keys.next() {
+            let java_key: JObject = utils::convert_bytes(env, &key)?.into();
+            let accepted = env
+                .call_method(self.predicate.as_obj(), "test", "([B)Z", &[java_key.into()])?
+                .z()?;
+            env.delete_local_ref(java_key)?;
+            if accepted {
+                return Ok(Some(key));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A native copy of a map value, see `nativeGetBytesView`.
+struct BytesView(Value);
 
 const JAVA_ENTRY_FQN: &str = "com/exonum/binding/storage/indices/MapEntryInternal";
+const JAVA_INDEX_STATS_FQN: &str = "com/exonum/binding/storage/indices/IndexStats";
+const BYTE_ARRAY: &str = "[B";
+
+/// The estimated storage overhead of a map entry in bytes, see `nativeApproxSizeBytes`.
+const ENTRY_OVERHEAD_BYTES: jlong = 16;
+
+/// The maximum length of the keys put into the maps, or zero if the length is unlimited,
+/// see `nativeSetMaxKeyLength`.
+static MAX_KEY_LENGTH: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// The status codes of `nativeTryPut`.
+const TRY_PUT_OK: jint = 0;
+const TRY_PUT_SNAPSHOT: jint = -1;
+const TRY_PUT_CONVERSION_ERROR: jint = -2;
+const TRY_PUT_INVALID_HANDLE: jint = -3;
+const TRY_PUT_KEY_TOO_LONG: jint = -4;
+const TRY_PUT_STORAGE_ERROR: jint = -5;
+
+const MERGE_OP_OVERWRITE: jint = 0;
+const MERGE_OP_APPEND: jint = 1;
+const MERGE_OP_ADD_I64: jint = 2;
+
+/// The operations of `nativeReduceValuesI64`.
+const REDUCE_OP_ADD: jint = 0;
+const REDUCE_OP_MIN: jint = 1;
+const REDUCE_OP_MAX: jint = 2;
+const REDUCE_OP_MUL: jint = 3;
+
+/// The file formats of `nativeDumpToFile`.
+const DUMP_FORMAT_BINARY: jint = 0;
+const DUMP_FORMAT_CSV_HEX: jint = 1;
 
 /// Returns a pointer to the created `MapIndex` object.
 #[no_mangle]
@@ -45,19 +252,49 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nat
 ) -> Handle {
     let res = panic::catch_unwind(|| {
         let name = utils::convert_to_string(&env, name)?;
-        Ok(utils::to_handle(
-            match *utils::cast_handle::<View>(view_handle).get() {
-                ViewRef::Snapshot(snapshot) => {
-                    IndexType::SnapshotIndex(Index::new(name, &*snapshot))
-                }
-                ViewRef::Fork(ref mut fork) => IndexType::ForkIndex(Index::new(name, fork)),
-            },
-        ))
+        let view = utils::cast_handle::<View>(view_handle);
+        let guard = view.guard();
+        let index = match *view.get() {
+            ViewRef::Snapshot(snapshot) => IndexType::SnapshotIndex(Index::new(name, &*snapshot)),
+            ViewRef::Fork(ref mut fork) => {
+                IndexType::ForkIndex(Index::new(name, fork), CachedSize::default())
+            }
+        };
+        Ok(utils::to_handle(IndexHandle::new(index, guard)))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the created read-only `MapIndex` object.
+///
+/// If the view is a fork, the index reads its current state, but cannot modify it:
+/// any attempt to do so throws an exception, as with the indices of snapshots.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateReadOnly(
+    env: JNIEnv,
+    _: JClass,
+    name: JString,
+    view_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let name = utils::convert_to_string(&env, name)?;
+        let view = utils::cast_handle::<View>(view_handle);
+        let guard = view.guard();
+        let snapshot: &'static Snapshot = match *view.get() {
+            ViewRef::Snapshot(snapshot) => snapshot,
+            ViewRef::Fork(ref fork) => &**fork,
+        };
+        let index = IndexType::SnapshotIndex(Index::new(name, snapshot));
+        Ok(utils::to_handle(IndexHandle::new(index, guard)))
     });
     utils::unwrap_exc_or_default(&env, res)
 }
 
 /// Returns a pointer to the created `MapIndex` instance in an index family (= group).
+///
+/// The id is composed with `utils::compose_family_id` as a single part, so the index is the same
+/// as the one created with `nativeCreateInGroupMulti(group_name, [map_id])`, and different ids
+/// never collide with each other or with the multi-part ids.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateInGroup(
     env: JNIEnv,
@@ -69,15 +306,86 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nat
     let res = panic::catch_unwind(|| {
         let group_name = utils::convert_to_string(&env, group_name)?;
         let map_id = env.convert_byte_array(map_id)?;
-        let view_ref = utils::cast_handle::<View>(view_handle).get();
-        Ok(utils::to_handle(match *view_ref {
+        let map_id = utils::compose_family_id(&[map_id.as_slice()]);
+        let view = utils::cast_handle::<View>(view_handle);
+        let guard = view.guard();
+        let index = match *view.get() {
             ViewRef::Snapshot(snapshot) => {
                 IndexType::SnapshotIndex(Index::new_in_family(group_name, &map_id, &*snapshot))
             }
-            ViewRef::Fork(ref mut fork) => {
-                IndexType::ForkIndex(Index::new_in_family(group_name, &map_id, fork))
+            ViewRef::Fork(ref mut fork) => IndexType::ForkIndex(
+                Index::new_in_family(group_name, &map_id, fork),
+                CachedSize::default(),
+            ),
+        };
+        Ok(utils::to_handle(IndexHandle::new(index, guard)))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the created `MapIndex` instance in an index family (= group)
+/// with the id composed of several parts.
+///
+/// The id is composed with `utils::compose_family_id`: it is the concatenation of the parts,
+/// each prefixed with its length encoded as a big-endian 4-byte integer, so that different
+/// sequences of parts never produce the same id.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateInGroupMulti(
+    env: JNIEnv,
+    _: JClass,
+    group_name: JString,
+    id_parts: jobjectArray,
+    view_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let group_name = utils::convert_to_string(&env, group_name)?;
+        let map_id = utils::convert_to_family_id(&env, id_parts)?;
+        let view = utils::cast_handle::<View>(view_handle);
+        let guard = view.guard();
+        let index = match *view.get() {
+            ViewRef::Snapshot(snapshot) => {
+                IndexType::SnapshotIndex(Index::new_in_family(group_name, &map_id, &*snapshot))
+            }
+            ViewRef::Fork(ref mut fork) => IndexType::ForkIndex(
+                Index::new_in_family(group_name, &map_id, fork),
+                CachedSize::default(),
+            ),
+        };
+        Ok(utils::to_handle(IndexHandle::new(index, guard)))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the created `MapIndex` instance with the given name in a shard.
+///
+/// The shard prefix is the id of the index in the family with the given name, encoded
+/// as a single part of `nativeCreateInGroupMulti`, so the indices of different shards
+/// never collide, and a sharded index is the same as the one created with
+/// `nativeCreateInGroupMulti(name, [shard_prefix])`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateSharded(
+    env: JNIEnv,
+    _: JClass,
+    shard_prefix: jbyteArray,
+    name: JString,
+    view_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let shard_prefix = env.convert_byte_array(shard_prefix)?;
+        let name = utils::convert_to_string(&env, name)?;
+        let map_id = utils::compose_family_id(&[shard_prefix.as_slice()]);
+        let view = utils::cast_handle::<View>(view_handle);
+        let guard = view.guard();
+        let index = match *view.get() {
+            ViewRef::Snapshot(snapshot) => {
+                IndexType::SnapshotIndex(Index::new_in_family(name, &map_id, &*snapshot))
             }
-        }))
+            ViewRef::Fork(ref mut fork) => IndexType::ForkIndex(
+                Index::new_in_family(name, &map_id, fork),
+                CachedSize::default(),
+            ),
+        };
+        Ok(utils::to_handle(IndexHandle::new(index, guard)))
     });
     utils::unwrap_exc_or_default(&env, res)
 }
@@ -89,7 +397,7 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nat
     _: JClass,
     map_handle: Handle,
 ) {
-    utils::drop_handle::<IndexType>(&env, map_handle);
+    utils::drop_handle::<IndexHandle>(&env, map_handle);
 }
 
 /// Returns value identified by the `key`. Null pointer is returned if value is not found.
@@ -101,294 +409,2282 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nat
     key: jbyteArray,
 ) -> jbyteArray {
     let res = panic::catch_unwind(|| {
-        let key = env.convert_byte_array(key)?;
-        let val = match *utils::cast_handle::<IndexType>(map_handle) {
-            IndexType::SnapshotIndex(ref map) => map.get(&key),
-            IndexType::ForkIndex(ref map) => map.get(&key),
-        };
-        match val {
-            Some(val) => env.byte_array_from_slice(&val),
-            None => Ok(ptr::null_mut()),
-        }
+        utils::measure(Operation::Get, || {
+            let key = env.convert_byte_array(key)?;
+            let val = match **utils::cast_handle::<IndexHandle>(map_handle) {
+                IndexType::SnapshotIndex(ref map) => map.get(&key),
+                IndexType::ForkIndex(ref map, _) => map.get(&key),
+            };
+            match val {
+                Some(val) => utils::convert_bytes(&env, &val),
+                None => Ok(ptr::null_mut()),
+            }
+        })
     });
     utils::unwrap_exc_or(&env, res, ptr::null_mut())
 }
 
-/// Returns `true` if the map contains a value for the specified key.
+/// Returns a pointer to the native copy of the value identified by the `key`, which can be
+/// read without copying it to the Java heap with `nativeBytesViewBuffer`.
+/// Zero is returned if value is not found.
+///
+/// The indices return the values by value, so the bytes are copied once, to the native
+/// memory, instead of twice, as with `nativeGet`. The copy does not depend on the map or
+/// its view and must be freed with `nativeFreeBytesView`.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeContainsKey(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeGetBytesView(
     env: JNIEnv,
     _: JObject,
     map_handle: Handle,
     key: jbyteArray,
-) -> jboolean {
+) -> Handle {
     let res = panic::catch_unwind(|| {
         let key = env.convert_byte_array(key)?;
-        Ok(match *utils::cast_handle::<IndexType>(map_handle) {
-            IndexType::SnapshotIndex(ref map) => map.contains(&key),
-            IndexType::ForkIndex(ref map) => map.contains(&key),
-        } as jboolean)
+        let val = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.get(&key),
+            IndexType::ForkIndex(ref map, _) => map.get(&key),
+        };
+        Ok(val.map_or(0, |val| utils::to_handle(BytesView(val))))
     });
     utils::unwrap_exc_or_default(&env, res)
 }
 
-/// Returns the pointer to the iterator over a map keys and values.
+/// Returns a direct `java.nio.ByteBuffer` backed by the value copy created by
+/// `nativeGetBytesView`.
+///
+/// The buffer is valid only until the copy is freed with `nativeFreeBytesView`: accessing it
+/// afterwards is undefined behaviour, so it must not escape the code owning the copy.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateEntriesIter(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeBytesViewBuffer(
     env: JNIEnv,
-    _: JObject,
-    map_handle: Handle,
-) -> Handle {
+    _: JClass,
+    bytes_view_handle: Handle,
+) -> jobject {
     let res = panic::catch_unwind(|| {
-        let iter = match *utils::cast_handle::<IndexType>(map_handle) {
-            IndexType::SnapshotIndex(ref map) => map.iter(),
-            IndexType::ForkIndex(ref map) => map.iter(),
-        };
-        let iter = Iter::new(&env, iter, JAVA_ENTRY_FQN)?;
-        Ok(utils::to_handle(iter))
+        let bytes = &mut utils::cast_handle::<BytesView>(bytes_view_handle).0;
+        Ok(env.new_direct_byte_buffer(bytes)?.into_inner())
     });
-    utils::unwrap_exc_or_default(&env, res)
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
 }
 
-/// Returns a pointer to the iterator over map keys.
+/// Destroys the value copy created by `nativeGetBytesView` and frees memory.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateKeysIter(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFreeBytesView(
     env: JNIEnv,
-    _: JObject,
-    map_handle: Handle,
-) -> Handle {
-    let res = panic::catch_unwind(|| {
-        Ok(utils::to_handle(
-            match *utils::cast_handle::<IndexType>(map_handle) {
-                IndexType::SnapshotIndex(ref map) => map.keys(),
-                IndexType::ForkIndex(ref map) => map.keys(),
-            },
-        ))
-    });
-    utils::unwrap_exc_or_default(&env, res)
+    _: JClass,
+    bytes_view_handle: Handle,
+) {
+    utils::drop_handle::<BytesView>(&env, bytes_view_handle);
 }
 
-/// Returns a pointer to the iterator over map values.
+/// Copies the value identified by the `key` into the given direct `java.nio.ByteBuffer`,
+/// starting at its beginning, and returns the number of copied bytes.
+///
+/// Returns -1 if value is not found, and `-needed_size` if the buffer is too small
+/// for the value, leaving the buffer intact. The buffer must not be empty, so that
+/// a one-byte value which does not fit is not reported as absent. The position and
+/// the limit of the buffer are not changed.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateValuesIter(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeGetInto(
     env: JNIEnv,
     _: JObject,
     map_handle: Handle,
-) -> Handle {
+    key: jbyteArray,
+    dest: JObject,
+) -> jint {
     let res = panic::catch_unwind(|| {
-        Ok(utils::to_handle(
-            match *utils::cast_handle::<IndexType>(map_handle) {
-                IndexType::SnapshotIndex(ref map) => map.values(),
-                IndexType::ForkIndex(ref map) => map.values(),
-            },
-        ))
+        utils::measure(Operation::Get, || {
+            let key = env.convert_byte_array(key)?;
+            let val = match **utils::cast_handle::<IndexHandle>(map_handle) {
+                IndexType::SnapshotIndex(ref map) => map.get(&key),
+                IndexType::ForkIndex(ref map, _) => map.get(&key),
+            };
+            let val = match val {
+                Some(val) => val,
+                None => return Ok(-1),
+            };
+            assert!(
+                val.len() <= jint::max_value() as usize,
+                "The value is too large"
+            );
+            let dest = env.get_direct_buffer_address(JByteBuffer::from(dest.into_inner()))?;
+            assert!(!dest.is_empty(), "The buffer is empty");
+            if dest.len() < val.len() {
+                return Ok(-(val.len() as jint));
+            }
+            dest[..val.len()].copy_from_slice(&val);
+            Ok(val.len() as jint)
+        })
     });
     utils::unwrap_exc_or_default(&env, res)
 }
 
-/// Returns the pointer to the iterator over a map keys and values starting at the given key.
+/// Returns the length in bytes of the value identified by the `key`, or -1 if the value
+/// is not found. The value is not copied to Java.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateIterFrom(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValueLength(
     env: JNIEnv,
     _: JObject,
     map_handle: Handle,
     key: jbyteArray,
-) -> Handle {
+) -> jlong {
     let res = panic::catch_unwind(|| {
         let key = env.convert_byte_array(key)?;
-        let iter = match *utils::cast_handle::<IndexType>(map_handle) {
-            IndexType::SnapshotIndex(ref map) => map.iter_from(&key),
-            IndexType::ForkIndex(ref map) => map.iter_from(&key),
+        let len = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.get(&key).map(|v| v.len()),
+            IndexType::ForkIndex(ref map, _) => map.get(&key).map(|v| v.len()),
         };
-        let iter = Iter::new(&env, iter, JAVA_ENTRY_FQN)?;
-        Ok(utils::to_handle(iter))
+        Ok(len.map_or(-1, |len| len as jlong))
     });
     utils::unwrap_exc_or_default(&env, res)
 }
 
-/// Returns a pointer to the iterator over map keys starting at the given key.
+/// Returns `length` bytes of the value identified by the `key` starting at `offset`.
+/// Null pointer is returned if value is not found.
+///
+/// Throws `IndexOutOfBoundsException` if the range does not lie within the value.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysFrom(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeGetValueRange(
     env: JNIEnv,
-    _: JClass,
+    _: JObject,
     map_handle: Handle,
     key: jbyteArray,
-) -> Handle {
+    offset: jlong,
+    length: jint,
+) -> jbyteArray {
     let res = panic::catch_unwind(|| {
         let key = env.convert_byte_array(key)?;
-        Ok(utils::to_handle(
-            match *utils::cast_handle::<IndexType>(map_handle) {
-                IndexType::SnapshotIndex(ref map) => map.keys_from(&key),
-                IndexType::ForkIndex(ref map) => map.keys_from(&key),
-            },
-        ))
+        let value = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.get(&key),
+            IndexType::ForkIndex(ref map, _) => map.get(&key),
+        };
+        let value = match value {
+            Some(value) => value,
+            None => return Ok(ptr::null_mut()),
+        };
+        let end = offset.checked_add(jlong::from(length));
+        match end {
+            Some(end) if offset >= 0 && length >= 0 && end <= value.len() as jlong => {
+                utils::convert_bytes(&env, &value[offset as usize..end as usize])
+            }
+            _ => {
+                let message = format!(
+                    "Range [{}, {} + {}) is out of bounds for the value of {} bytes",
+                    offset,
+                    offset,
+                    length,
+                    value.len()
+                );
+                utils::throw_and_fail(&env, INDEX_OUT_OF_BOUNDS_EXCEPTION, message)
+            }
+        }
     });
-    utils::unwrap_exc_or_default(&env, res)
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
 }
 
-/// Returns a pointer to the iterator over map values starting at the given key.
+/// Returns `true` if the map contains a value for the specified key.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValuesFrom(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeContainsKey(
     env: JNIEnv,
-    _: JClass,
+    _: JObject,
     map_handle: Handle,
     key: jbyteArray,
-) -> Handle {
+) -> jboolean {
     let res = panic::catch_unwind(|| {
         let key = env.convert_byte_array(key)?;
-        Ok(utils::to_handle(
-            match *utils::cast_handle::<IndexType>(map_handle) {
-                IndexType::SnapshotIndex(ref map) => map.values_from(&key),
-                IndexType::ForkIndex(ref map) => map.values_from(&key),
-            },
-        ))
+        Ok(match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.contains(&key),
+            IndexType::ForkIndex(ref map, _) => map.contains(&key),
+        } as jboolean)
     });
     utils::unwrap_exc_or_default(&env, res)
 }
 
-/// Sets `value` identified by the `key` into the index.
+/// Returns value identified by the `key`. The `default_value` is returned if value
+/// is not found, which may be null.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativePut(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeGetWithDefault(
     env: JNIEnv,
     _: JObject,
     map_handle: Handle,
     key: jbyteArray,
-    value: jbyteArray,
-) {
-    let res = panic::catch_unwind(|| match *utils::cast_handle::<IndexType>(map_handle) {
-        IndexType::SnapshotIndex(_) => {
-            panic!("Unable to modify snapshot.");
-        }
-        IndexType::ForkIndex(ref mut map) => {
-            let key = env.convert_byte_array(key)?;
-            let value = env.convert_byte_array(value)?;
-            map.put(&key, value);
-            Ok(())
+    default_value: jbyteArray,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let key = env.convert_byte_array(key)?;
+        let val = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.get(&key),
+            IndexType::ForkIndex(ref map, _) => map.get(&key),
+        };
+        match val {
+            Some(val) => utils::convert_bytes(&env, &val),
+            None => Ok(default_value),
         }
     });
-    utils::unwrap_exc_or_default(&env, res)
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
 }
 
-/// Removes value identified by the `key` from the index.
+/// Returns an array of the values identified by the `keys`, in the same order, where
+/// the keys that have no value get the `default_value`.
+///
+/// The `default_value` array itself is put in each such slot, so the slots share it.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeRemove(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeGetAllOrDefault(
     env: JNIEnv,
     _: JObject,
     map_handle: Handle,
-    key: jbyteArray,
-) {
-    let res = panic::catch_unwind(|| match *utils::cast_handle::<IndexType>(map_handle) {
-        IndexType::SnapshotIndex(_) => {
-            panic!("Unable to modify snapshot.");
-        }
-        IndexType::ForkIndex(ref mut map) => {
-            let key = env.convert_byte_array(key)?;
-            map.remove(&key);
-            Ok(())
+    keys: jobjectArray,
+    default_value: jbyteArray,
+) -> jobjectArray {
+    let res = panic::catch_unwind(|| {
+        let keys = utils::convert_to_byte_arrays(&env, keys)?;
+        let values: Vec<Option<Value>> = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => keys.iter().map(|key| map.get(key)).collect(),
+            IndexType::ForkIndex(ref map, _) => keys.iter().map(|key| map.get(key)).collect(),
+        };
+        let default_value = JObject::from(default_value);
+        let result = env.new_object_array(values.len() as jsize, BYTE_ARRAY, JObject::null())?;
+        for (i, value) in values.iter().enumerate() {
+            match *value {
+                Some(ref value) => {
+                    let value: JObject = utils::convert_bytes(&env, value)?.into();
+                    env.set_object_array_element(result, i as jsize, value)?;
+                    // The arrays are released right away, as the number of local references
+                    // is limited.
+                    env.delete_local_ref(value)?;
+                }
+                None => env.set_object_array_element(result, i as jsize, default_value)?,
+            }
         }
+        Ok(result)
     });
-    utils::unwrap_exc_or_default(&env, res)
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
 }
 
-/// Clears the index, removing all values.
+/// Returns value identified by the `i64` key. Null pointer is returned if value is not found.
+///
+/// The `i64` keys are stored as 8-byte big-endian arrays, so that the order of non-negative
+/// keys in the map matches their numeric order.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeClear(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeGetByLongKey(
     env: JNIEnv,
     _: JObject,
     map_handle: Handle,
-) {
-    let res = panic::catch_unwind(|| match *utils::cast_handle::<IndexType>(map_handle) {
-        IndexType::SnapshotIndex(_) => {
-            panic!("Unable to modify snapshot.");
-        }
-        IndexType::ForkIndex(ref mut map) => {
-            map.clear();
-            Ok(())
-        }
-    });
-    utils::unwrap_exc_or_default(&env, res)
-}
-
-/// Returns the next value from the iterator. Returns null pointer when iteration is finished.
-#[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeEntriesIterNext(
-    env: JNIEnv,
-    _: JObject,
-    iter_handle: Handle,
-) -> jobject {
+    key: jlong,
+) -> jbyteArray {
     let res = panic::catch_unwind(|| {
-        let iterWrapper = utils::cast_handle::<Iter>(iter_handle);
-        match iterWrapper.iter.next() {
-            Some(val) => {
-                let key: JObject = env.byte_array_from_slice(&val.0)?.into();
-                let value: JObject = env.byte_array_from_slice(&val.1)?.into();
-                Ok(env
-                    .new_object_by_id(
-                        &iterWrapper.element_class,
-                        iterWrapper.constructor_id,
-                        &[key.into(), value.into()],
-                    )?.into_inner())
-            }
+        let key = utils::convert_long_key(key);
+        let val = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.get(&key),
+            IndexType::ForkIndex(ref map, _) => map.get(&key),
+        };
+        match val {
+            Some(val) => utils::convert_bytes(&env, &val),
             None => Ok(ptr::null_mut()),
         }
     });
     utils::unwrap_exc_or(&env, res, ptr::null_mut())
 }
 
-/// Destroys the underlying `MapIndex` iterator object and frees memory.
+/// Returns `true` if the map contains a value for the specified `i64` key.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeEntriesIterFree(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeContainsLongKey(
     env: JNIEnv,
     _: JObject,
-    iter_handle: Handle,
-) {
-    utils::drop_handle::<Iter>(&env, iter_handle);
+    map_handle: Handle,
+    key: jlong,
+) -> jboolean {
+    let res = panic::catch_unwind(|| {
+        let key = utils::convert_long_key(key);
+        Ok(match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.contains(&key),
+            IndexType::ForkIndex(ref map, _) => map.contains(&key),
+        } as jboolean)
+    });
+    utils::unwrap_exc_or_default(&env, res)
 }
 
-/// Returns the next value from the keys-iterator. Returns null pointer when iteration is finished.
+/// Returns `true` if the map contains a key starting with the given prefix.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysIterNext(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativePrefixExists(
     env: JNIEnv,
     _: JObject,
-    iter_handle: Handle,
-) -> jbyteArray {
+    map_handle: Handle,
+    prefix: jbyteArray,
+) -> jboolean {
     let res = panic::catch_unwind(|| {
-        let iter = utils::cast_handle::<MapIndexKeys<Key>>(iter_handle);
-        match iter.next() {
-            Some(val) => env.byte_array_from_slice(&val),
-            None => Ok(ptr::null_mut()),
-        }
+        let prefix = env.convert_byte_array(prefix)?;
+        // The first key not less than the prefix is the least key starting with it, if any.
+        let key = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.keys_from(&prefix).next(),
+            IndexType::ForkIndex(ref map, _) => map.keys_from(&prefix).next(),
+        };
+        Ok(key.map_or(false, |key| key.starts_with(&prefix)) as jboolean)
     });
-    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+    utils::unwrap_exc_or_default(&env, res)
 }
 
-/// Destroys the underlying `MapIndex` keys-iterator object and frees memory.
+/// Returns `true` if the map contains a key in the range `[from, to)`. The range is unbounded
+/// if `to` is null.
+///
+/// Only the first key not less than `from` is read, so the range is never iterated.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysIterFree(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeRangeNonEmpty(
     env: JNIEnv,
     _: JObject,
-    iter_handle: Handle,
-) {
-    utils::drop_handle::<MapIndexKeys<Key>>(&env, iter_handle);
+    map_handle: Handle,
+    from: jbyteArray,
+    to: jbyteArray,
+) -> jboolean {
+    let res = panic::catch_unwind(|| {
+        let from = env.convert_byte_array(from)?;
+        let to = if to.is_null() {
+            None
+        } else {
+            Some(env.convert_byte_array(to)?)
+        };
+        let key = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.keys_from(&from).next(),
+            IndexType::ForkIndex(ref map, _) => map.keys_from(&from).next(),
+        };
+        let non_empty = match (key, to) {
+            (Some(key), Some(to)) => key < to,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        Ok(non_empty as jboolean)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns `true` if the map contains a value for at least one of the specified keys.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeContainsAnyKey(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    keys: jobjectArray,
+) -> jboolean {
+    let res = panic::catch_unwind(|| {
+        let keys = utils::convert_to_byte_arrays(&env, keys)?;
+        Ok(match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => keys.iter().any(|key| map.contains(key)),
+            IndexType::ForkIndex(ref map, _) => keys.iter().any(|key| map.contains(key)),
+        } as jboolean)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns `true` if the map contains values for all the specified keys.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeContainsAllKeys(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    keys: jobjectArray,
+) -> jboolean {
+    let res = panic::catch_unwind(|| {
+        let keys = utils::convert_to_byte_arrays(&env, keys)?;
+        Ok(match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => keys.iter().all(|key| map.contains(key)),
+            IndexType::ForkIndex(ref map, _) => keys.iter().all(|key| map.contains(key)),
+        } as jboolean)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a bitmap of the presence of the specified keys in the map: bit `i % 8`
+/// of byte `i / 8` is set iff the map contains a value for `keys[i]`. The array has
+/// `(keys.length + 7) / 8` bytes; the unused high bits of the last byte are zero.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeContainsKeys(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    keys: jobjectArray,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let keys = utils::convert_to_byte_arrays(&env, keys)?;
+        let bitmap = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => presence_bitmap(&keys, |key| map.contains(key)),
+            IndexType::ForkIndex(ref map, _) => presence_bitmap(&keys, |key| map.contains(key)),
+        };
+        env.byte_array_from_slice(&bitmap)
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns `true` if any key of the map is associated with the specified value.
+///
+/// Note that this is an O(n) scan over all values of the map, intended for small maps
+/// and admin tooling rather than hot paths.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeContainsValue(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    value: jbyteArray,
+) -> jboolean {
+    let res = panic::catch_unwind(|| {
+        let value = env.convert_byte_array(value)?;
+        Ok(match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.values().any(|v| v == value),
+            IndexType::ForkIndex(ref map, _) => map.values().any(|v| v == value),
+        } as jboolean)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the smallest key of the map. Null pointer is returned if the map is empty.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFirstKey(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let key = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.keys().next(),
+            IndexType::ForkIndex(ref map, _) => map.keys().next(),
+        };
+        match key {
+            Some(key) => utils::convert_bytes(&env, &key),
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the largest key of the map. Null pointer is returned if the map is empty.
+///
+/// As the storage iterators are not double-ended, this method iterates over all the keys.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeLastKey(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let key = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.keys().last(),
+            IndexType::ForkIndex(ref map, _) => map.keys().last(),
+        };
+        match key {
+            Some(key) => utils::convert_bytes(&env, &key),
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the smallest key of the map that is greater than or equal to `target`.
+/// Null pointer is returned if there is no such key.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCeilingKey(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    target: jbyteArray,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let target = env.convert_byte_array(target)?;
+        let key = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.keys_from(&target).next(),
+            IndexType::ForkIndex(ref map, _) => map.keys_from(&target).next(),
+        };
+        match key {
+            Some(key) => utils::convert_bytes(&env, &key),
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the largest key of the map that is less than or equal to `target`.
+/// Null pointer is returned if there is no such key.
+///
+/// As the storage iterators are not double-ended, this method iterates over all the keys
+/// up to `target`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFloorKey(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    target: jbyteArray,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let target = env.convert_byte_array(target)?;
+        let key = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => floor_key(map, &target),
+            IndexType::ForkIndex(ref map, _) => floor_key(map, &target),
+        };
+        match key {
+            Some(key) => utils::convert_bytes(&env, &key),
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the key at the given position in the sorted sequence of the map keys.
+/// Null pointer is returned if the position is out of range.
+///
+/// The map is not positionally indexed, so this method iterates over `position` keys.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeyAtPosition(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    position: jlong,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        if position < 0 {
+            return Ok(ptr::null_mut());
+        }
+        let position = position as usize;
+        let key = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.keys().nth(position),
+            IndexType::ForkIndex(ref map, _) => map.keys().nth(position),
+        };
+        match key {
+            Some(key) => utils::convert_bytes(&env, &key),
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the number of keys in the range `[from, to)`. The range is unbounded
+/// if `to` is null, and empty if `from` is greater than `to`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCountKeysBetween(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    from: jbyteArray,
+    to: jbyteArray,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        let from = env.convert_byte_array(from)?;
+        let to = if to.is_null() {
+            None
+        } else {
+            Some(env.convert_byte_array(to)?)
+        };
+        let count = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => count_keys_between(map, &from, to.as_ref()),
+            IndexType::ForkIndex(ref map, _) => count_keys_between(map, &from, to.as_ref()),
+        };
+        Ok(count as jlong)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Reads all the entries of the map to load them into the caches of the database
+/// and the OS, so that the subsequent requests do not hit the disk.
+///
+/// The entries are read in the order of keys and discarded, so the call is cheap if they are
+/// already cached. For a `MemoryDB` all the data is in memory, so this is a no-op apart from
+/// the iteration itself.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeWarmup(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) {
+    let res = panic::catch_unwind(|| {
+        warm_up_map(map_handle, &Key::new(), None);
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Reads the entries of the map in the range `[from, to)` to load them into the caches,
+/// see `nativeWarmup`. The range is unbounded if `to` is null.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeWarmupRange(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    from: jbyteArray,
+    to: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| {
+        let from = env.convert_byte_array(from)?;
+        let to = if to.is_null() {
+            None
+        } else {
+            Some(env.convert_byte_array(to)?)
+        };
+        warm_up_map(map_handle, &from, to.as_ref());
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a fingerprint of the map content, which is the same for maps with the same entries.
+///
+/// The fingerprint is computed by folding the entries into a running hash in the iteration
+/// order, which is the lexicographic order of keys, so it is deterministic. It is not
+/// a Merkle root and cannot be used in proofs; comparing fingerprints just allows to skip
+/// a full comparison of the maps.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeContentHash(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let hash = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => content_hash(map.iter()),
+            IndexType::ForkIndex(ref map, _) => content_hash(map.iter()),
+        };
+        utils::convert_hash(&env, &hash)
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the sum of the map values, each treated as a little-endian `i64`.
+///
+/// Throws `IllegalArgumentException` naming the key if a value is not an 8-byte integer,
+/// and `ArithmeticException` if the sum overflows.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValuesSumI64(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        let map = &**utils::cast_handle::<IndexHandle>(map_handle);
+        reduce_map_i64(&env, map, REDUCE_OP_ADD, 0)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Folds the map values, each treated as a little-endian `i64`, with the operation `op`
+/// starting with `initial`, and returns the result, which is `initial` for an empty map.
+///
+/// The operation is one of `REDUCE_OP_ADD`, `REDUCE_OP_MIN`, `REDUCE_OP_MAX` and `REDUCE_OP_MUL`.
+/// Throws `IllegalArgumentException` naming the key if a value is not an 8-byte integer,
+/// and `ArithmeticException` if the sum or the product overflows. Panics if the operation
+/// is unknown.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeReduceValuesI64(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    op: jint,
+    initial: jlong,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        let map = &**utils::cast_handle::<IndexHandle>(map_handle);
+        reduce_map_i64(&env, map, op, initial)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns an estimate of the number of bytes the map occupies in the storage: the total
+/// length of its keys and values plus `ENTRY_OVERHEAD_BYTES` per entry.
+///
+/// The estimate does not account for the storage engine compression and metadata,
+/// and is intended for monitoring only. The engine own estimates are not used, as the
+/// databases do not expose them.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeApproxSizeBytes(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        Ok(match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => approx_size_bytes(map.iter()),
+            IndexType::ForkIndex(ref map, _) => approx_size_bytes(map.iter()),
+        })
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the numbers of the values of the map binned by their length.
+///
+/// The buckets are delimited by the boundaries, which must be non-negative and strictly
+/// ascending: with boundaries `b0, ..., bn` the buckets are `[0, b0)`, `[b0, b1)`, ...,
+/// `[bn, +inf)`, so the returned array has one more element than `bucket_boundaries`.
+/// Throws `IllegalArgumentException` if the boundaries are not valid.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValueSizeHistogram(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    bucket_boundaries: jlongArray,
+) -> jlongArray {
+    let res = panic::catch_unwind(|| {
+        let len = env.get_array_length(bucket_boundaries)?;
+        let mut boundaries = vec![0; len as usize];
+        env.get_long_array_region(bucket_boundaries, 0, &mut boundaries)?;
+        let valid = boundaries.first().map_or(true, |&first| first >= 0)
+            && boundaries.windows(2).all(|pair| pair[0] < pair[1]);
+        if !valid {
+            let message = format!("Invalid bucket boundaries: {:?}", boundaries);
+            return utils::throw_and_fail(&env, ILLEGAL_ARGUMENT_EXCEPTION, message);
+        }
+        let counts = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => value_size_histogram(map.values(), &boundaries),
+            IndexType::ForkIndex(ref map, _) => value_size_histogram(map.values(), &boundaries),
+        };
+        let histogram = env.new_long_array(counts.len() as jsize)?;
+        env.set_long_array_region(histogram, 0, &counts)?;
+        Ok(histogram)
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns an `IndexStats` object with the number of entries of the map, the estimate
+/// of its size in bytes as of `nativeApproxSizeBytes`, and its first and last keys,
+/// which are null if the map is empty.
+///
+/// All the stats are collected in a single pass over the entries, so they are consistent
+/// with each other.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeGetStats(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> jobject {
+    let res = panic::catch_unwind(|| {
+        let stats = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => index_stats(map.iter()),
+            IndexType::ForkIndex(ref map, _) => index_stats(map.iter()),
+        };
+        let first_key = match stats.first_key {
+            Some(ref key) => utils::convert_bytes(&env, key)?.into(),
+            None => JObject::null(),
+        };
+        let last_key = match stats.last_key {
+            Some(ref key) => utils::convert_bytes(&env, key)?.into(),
+            None => JObject::null(),
+        };
+        let stats = env.new_object(
+            JAVA_INDEX_STATS_FQN,
+            "(JJ[B[B)V",
+            &[
+                stats.size.into(),
+                stats.approx_size_bytes.into(),
+                first_key.into(),
+                last_key.into(),
+            ],
+        )?;
+        Ok(stats.into_inner())
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns an array of `MapEntryInternal` with all the entries of the map.
+///
+/// Throws `IllegalArgumentException` if `max_entries` is negative or the map has more entries.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeToEntriesArray(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    max_entries: jint,
+) -> jobjectArray {
+    let res = panic::catch_unwind(|| {
+        let max_entries = check_max_entries(&env, max_entries)?;
+        match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => to_entries_array(&env, map, max_entries),
+            IndexType::ForkIndex(ref map, _) => to_entries_array(&env, map, max_entries),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns an array of `MapEntryInternal` with the copies of all the entries of the map,
+/// which remains valid after the map, its view and the database are destroyed.
+///
+/// Unlike `nativeToEntriesArray`, the entries are read in a single pass into a native copy
+/// before any Java object is created. `IllegalArgumentException` is thrown if `max_entries`
+/// is negative or the map has more entries, instead of truncating the copy.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeMaterialize(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    max_entries: jint,
+) -> jobjectArray {
+    let res = panic::catch_unwind(|| {
+        let max_entries = check_max_entries(&env, max_entries)?;
+        // One entry more than the maximum is read to tell if the map exceeds it.
+        let limit = max_entries + 1;
+        let entries: Vec<(Key, Value)> = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.iter().take(limit).collect(),
+            IndexType::ForkIndex(ref map, _) => map.iter().take(limit).collect(),
+        };
+        if entries.len() > max_entries {
+            let message = format!("The map has more than {} entries", max_entries);
+            return utils::throw_and_fail(&env, ILLEGAL_ARGUMENT_EXCEPTION, message);
+        }
+        new_entries_array(&env, entries.len(), entries.into_iter())
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the entries of the map as a JSON object, which maps the encoded keys to the encoded
+/// values. The keys and the values are encoded as hex strings if `hex` is `true`, or as base64
+/// strings otherwise.
+///
+/// This is a human-facing diagnostic for small maps, not intended for large indices: all
+/// the entries are copied into a single string. Throws `IllegalArgumentException`
+/// if `max_entries` is negative or the map has more entries.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeExportJson(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    max_entries: jint,
+    hex: jboolean,
+) -> jstring {
+    let res = panic::catch_unwind(|| {
+        let max_entries = check_max_entries(&env, max_entries)?;
+        let encode: fn(&[u8]) -> String = if hex == JNI_TRUE {
+            utils::to_hex
+        } else {
+            utils::to_base64
+        };
+        // One more entry than allowed is taken to detect the maps that are too large.
+        let entries: Vec<(Key, Value)> = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.iter().take(max_entries + 1).collect(),
+            IndexType::ForkIndex(ref map, _) => map.iter().take(max_entries + 1).collect(),
+        };
+        if entries.len() > max_entries {
+            let message = format!("The map has more than {} entries", max_entries);
+            return utils::throw_and_fail(&env, ILLEGAL_ARGUMENT_EXCEPTION, message);
+        }
+        let json: serde_json::Map<String, serde_json::Value> = entries
+            .iter()
+            .map(|&(ref key, ref value)| (encode(key), encode(value).into()))
+            .collect();
+        let json = serde_json::to_string(&json).expect("Unable to serialize entries");
+        Ok(env.new_string(json)?.into_inner())
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Writes all the entries of the map to the file at the given path, replacing its content.
+///
+/// With `DUMP_FORMAT_BINARY` each entry is written as the key frame followed by the value
+/// frame, where a frame is the length of the bytes as a big-endian `u32` followed by the bytes.
+/// With `DUMP_FORMAT_CSV_HEX` each entry is a `key,value` line with the hex-encoded bytes.
+/// An I/O error is thrown as `IOException`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeDumpToFile(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    path: JString,
+    format: jint,
+) {
+    let res = panic::catch_unwind(|| {
+        let path = utils::convert_to_string(&env, path)?;
+        let result = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => dump_to_file(&path, map.iter(), format),
+            IndexType::ForkIndex(ref map, _) => dump_to_file(&path, map.iter(), format),
+        };
+        if let Err(e) = result {
+            let message = format!("Unable to dump the map to {}: {}", path, e);
+            return utils::throw_and_fail(&env, IO_EXCEPTION, message);
+        }
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a two-element array of `MapEntryInternal` with the first and the last entries
+/// of the map, which are nulls if the map is empty.
+///
+/// The storage iterators are not double-ended, so the last entry is found by iterating
+/// over all the entries.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFirstAndLastEntry(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> jobjectArray {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(ref map) => first_and_last_entries(&env, map),
+        IndexType::ForkIndex(ref map, _) => first_and_last_entries(&env, map),
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Invokes `boolean accept(byte[] key, byte[] value)` of the given callback for each entry
+/// of the map in the order of keys. The iteration stops as soon as the callback
+/// returns `false`.
+///
+/// An exception thrown by the callback aborts the iteration and is propagated to the caller.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeForEach(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    callback: JObject,
+) {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(ref map) => for_each(&env, map, callback),
+        IndexType::ForkIndex(ref map, _) => for_each(&env, map, callback),
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Folds the entries of the map in the order of keys, starting with the `initial`
+/// accumulator and replacing it with the result of
+/// `Object apply(Object acc, byte[] key, byte[] value)` of the given accumulator function
+/// for each entry. Returns the final accumulator, which is `initial` for an empty map.
+///
+/// An exception thrown by the accumulator function aborts the iteration and is propagated
+/// to the caller.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFoldEntries(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    initial: JObject,
+    accumulator: JObject,
+) -> jobject {
+    let res = panic::catch_unwind(|| {
+        let result = match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => fold_entries(&env, map, initial, accumulator),
+            IndexType::ForkIndex(ref map, _) => fold_entries(&env, map, initial, accumulator),
+        }?;
+        Ok(result.into_inner())
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the minimum of the map values, each treated as a little-endian `i64`.
+///
+/// Throws `NoSuchElementException` if the map is empty, and `IllegalArgumentException`
+/// naming the key if a value is not an 8-byte integer.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeMinValueI64(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        let map = &**utils::cast_handle::<IndexHandle>(map_handle);
+        check_map_not_empty(&env, map)?;
+        reduce_map_i64(&env, map, REDUCE_OP_MIN, i64::max_value())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the maximum of the map values, each treated as a little-endian `i64`.
+///
+/// Throws `NoSuchElementException` if the map is empty, and `IllegalArgumentException`
+/// naming the key if a value is not an 8-byte integer.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeMaxValueI64(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        let map = &**utils::cast_handle::<IndexHandle>(map_handle);
+        check_map_not_empty(&env, map)?;
+        reduce_map_i64(&env, map, REDUCE_OP_MAX, i64::min_value())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the pointer to the iterator over a map keys and values.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateEntriesIter(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let map = utils::cast_handle::<IndexHandle>(map_handle);
+        let iter = match **map {
+            IndexType::SnapshotIndex(ref map) => map.iter(),
+            IndexType::ForkIndex(ref map, _) => map.iter(),
+        };
+        let iter = PairIter::new(&env, iter, JAVA_ENTRY_FQN)?;
+        Ok(utils::to_handle(Iter::new(Entries::new(iter), map.guard())))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the pointer to the iterator over a map keys and values, which are returned
+/// as objects of the given class instead of `MapEntryInternal`.
+///
+/// `class_name` is the name of the class in the JNI format, e.g., `com/example/Entry`, and
+/// `constructor_signature` is the signature of its constructor, which must accept the key
+/// and the value as two byte arrays.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateEntriesIterWithElementClass(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    class_name: JString,
+    constructor_signature: JString,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let class_name = utils::convert_to_string(&env, class_name)?;
+        let signature = utils::convert_to_string(&env, constructor_signature)?;
+        let map = utils::cast_handle::<IndexHandle>(map_handle);
+        let iter = match **map {
+            IndexType::SnapshotIndex(ref map) => map.iter(),
+            IndexType::ForkIndex(ref map, _) => map.iter(),
+        };
+        let iter = PairIter::with_constructor(&env, iter, &class_name, &signature)?;
+        Ok(utils::to_handle(Iter::new(Entries::new(iter), map.guard())))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the iterator over map keys.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateKeysIter(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let origin = KeysOrigin {
+            map_handle,
+            from: None,
+            reverse: false,
+        };
+        Ok(utils::to_handle(create_keys_iter(origin)))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the iterator over map values.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateValuesIter(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let map = utils::cast_handle::<IndexHandle>(map_handle);
+        let iter = match **map {
+            IndexType::SnapshotIndex(ref map) => map.values(),
+            IndexType::ForkIndex(ref map, _) => map.values(),
+        };
+        Ok(utils::to_handle(Values::new(Box::new(iter), map.guard())))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the iterator over map keys in the descending order,
+/// consumed with `nativeKeysIterNext`.
+///
+/// The storage iterators are not double-ended, so all the keys are read into memory
+/// when the iterator is created.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateKeysIterReverse(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let origin = KeysOrigin {
+            map_handle,
+            from: None,
+            reverse: true,
+        };
+        Ok(utils::to_handle(create_keys_iter(origin)))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the iterator over map values in the descending order of their keys,
+/// consumed with `nativeValuesIterNext`.
+///
+/// The storage iterators are not double-ended, so all the values are read into memory
+/// when the iterator is created.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateValuesIterReverse(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let map = utils::cast_handle::<IndexHandle>(map_handle);
+        let values: Vec<Value> = match **map {
+            IndexType::SnapshotIndex(ref map) => map.values().collect(),
+            IndexType::ForkIndex(ref map, _) => map.values().collect(),
+        };
+        let iter = Box::new(values.into_iter().rev());
+        Ok(utils::to_handle(Values::new(iter, map.guard())))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the pointer to the iterator over a map keys and values starting at the given key.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateIterFrom(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let key = env.convert_byte_array(key)?;
+        let map = utils::cast_handle::<IndexHandle>(map_handle);
+        let iter = match **map {
+            IndexType::SnapshotIndex(ref map) => map.iter_from(&key),
+            IndexType::ForkIndex(ref map, _) => map.iter_from(&key),
+        };
+        let iter = PairIter::new(&env, iter, JAVA_ENTRY_FQN)?;
+        Ok(utils::to_handle(Iter::new(Entries::new(iter), map.guard())))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the pointer to the iterator over a map keys and values starting just past
+/// the given key, which is skipped if present. Together with `nativeIterCursor` allows
+/// to resume an iteration without keeping the iterator.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCreateIterAfter(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let key = env.convert_byte_array(key)?;
+        let map = utils::cast_handle::<IndexHandle>(map_handle);
+        let iter = match **map {
+            IndexType::SnapshotIndex(ref map) => map.iter_from(&key),
+            IndexType::ForkIndex(ref map, _) => map.iter_from(&key),
+        };
+        let mut entries = Entries::new(PairIter::new(&env, iter, JAVA_ENTRY_FQN)?);
+        let at_key = match entries.pairs.iter.peek() {
+            Some(&(ref next_key, _)) => *next_key == key,
+            None => false,
+        };
+        if at_key {
+            entries.next();
+        }
+        entries.last_key = Some(key);
+        Ok(utils::to_handle(Iter::new(entries, map.guard())))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the iterator over map keys starting at the given key.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysFrom(
+    env: JNIEnv,
+    _: JClass,
+    map_handle: Handle,
+    key: jbyteArray,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let key = env.convert_byte_array(key)?;
+        let origin = KeysOrigin {
+            map_handle,
+            from: Some(key),
+            reverse: false,
+        };
+        Ok(utils::to_handle(create_keys_iter(origin)))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the iterator over map values starting at the given key.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValuesFrom(
+    env: JNIEnv,
+    _: JClass,
+    map_handle: Handle,
+    key: jbyteArray,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let key = env.convert_byte_array(key)?;
+        let map = utils::cast_handle::<IndexHandle>(map_handle);
+        let iter = match **map {
+            IndexType::SnapshotIndex(ref map) => map.values_from(&key),
+            IndexType::ForkIndex(ref map, _) => map.values_from(&key),
+        };
+        Ok(utils::to_handle(Values::new(Box::new(iter), map.guard())))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns pointer to the iterator over the values of the keys in the range `[from, to)`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValuesBetween(
+    env: JNIEnv,
+    _: JClass,
+    map_handle: Handle,
+    from: jbyteArray,
+    to: jbyteArray,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let from = env.convert_byte_array(from)?;
+        let to = env.convert_byte_array(to)?;
+        let map = utils::cast_handle::<IndexHandle>(map_handle);
+        let entries = match **map {
+            IndexType::SnapshotIndex(ref map) => map.iter_from(&from),
+            IndexType::ForkIndex(ref map, _) => map.iter_from(&from),
+        };
+        let iter = ValuesBetween { entries, to };
+        Ok(utils::to_handle(ValuesInRange::new(iter, map.guard())))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns pointer to the iterator over the keys for which the `test(byte[])` method
+/// of the predicate returns `true`. The predicate is called from `nativeFilterKeysNext`
+/// for each key of the map in turn.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFilterKeys(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    predicate: JObject,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let predicate = env.new_global_ref(predicate)?;
+        let map = utils::cast_handle::<IndexHandle>(map_handle);
+        let keys = match **map {
+            IndexType::SnapshotIndex(ref map) => map.keys(),
+            IndexType::ForkIndex(ref map, _) => map.keys(),
+        };
+        let iter = KeysFilter {
+            keys,
+            predicate,
+            aborted: false,
+        };
+        Ok(utils::to_handle(FilteredKeys::new(iter, map.guard())))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Sets the maximum length of the keys put into the maps, or makes it unlimited, which is
+/// the default, if `len` is zero. Putting a longer key throws `IllegalArgumentException`.
+///
+/// The limit applies to all the maps, and is checked by `nativePut`, `nativePutReturningSize`,
+/// `nativeMergeOp`, `nativeGetOrCompute`, `nativeCompareAndSet`, `nativeReplaceAll`,
+/// `nativePutAllPacked`, `nativeMoveEntry`, `nativeIncrement` and `nativeRekey`;
+/// `nativeTryPut` returns `TRY_PUT_KEY_TOO_LONG`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeSetMaxKeyLength(
+    env: JNIEnv,
+    _: JClass,
+    len: jint,
+) {
+    let res = panic::catch_unwind(|| {
+        assert!(len >= 0, "Negative maximum key length: {}", len);
+        MAX_KEY_LENGTH.store(len as usize, Ordering::Relaxed);
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Sets `value` identified by the `key` into the index.
+///
+/// Throws `NullPointerException` if the `key` or the `value` is null: they may be empty,
+/// but not absent.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativePut(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+    value: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| {
+        check_not_null(&env, key, "key")?;
+        check_not_null(&env, value, "value")?;
+        utils::measure(Operation::Put, || {
+            match **utils::cast_handle::<IndexHandle>(map_handle) {
+                IndexType::SnapshotIndex(_) => {
+                    panic!("Unable to modify snapshot.");
+                }
+                IndexType::ForkIndex(ref mut map, ref mut size) => {
+                    let key = env.convert_byte_array(key)?;
+                    let value = env.convert_byte_array(value)?;
+                    check_key_length(&env, &key)?;
+                    on_put(map, size, &key);
+                    map.put(&key, value);
+                    Ok(())
+                }
+            }
+        })
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Puts the entries from a single packed buffer into the index. The buffer is a sequence
+/// of entries, each being the key frame followed by the value frame, where a frame is
+/// the length of the content as a big-endian `u32` followed by the content
+/// (see `utils::write_frame`).
+///
+/// The whole buffer is parsed before the index is modified: if it is truncated,
+/// `IllegalArgumentException` is thrown and no entries are put.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativePutAllPacked(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    data: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map, ref mut size) => {
+            let data = env.convert_byte_array(data)?;
+            let entries = match parse_packed_entries(&data) {
+                Some(entries) => entries,
+                None => {
+                    return utils::throw_and_fail(
+                        &env,
+                        ILLEGAL_ARGUMENT_EXCEPTION,
+                        "Truncated packed entries",
+                    );
+                }
+            };
+            for &(ref key, _) in &entries {
+                check_key_length(&env, key)?;
+            }
+            for (key, value) in entries {
+                on_put(map, size, &key);
+                map.put(&key, value);
+            }
+            Ok(())
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Sets `value` identified by the `key` into the index, returning a status code instead
+/// of throwing an exception on failure: `TRY_PUT_OK` or one of negative error codes.
+///
+/// The pending exceptions of the failed conversions of the arguments are cleared.
+/// A panic while modifying the index is reported as `TRY_PUT_STORAGE_ERROR`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeTryPut(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+    value: jbyteArray,
+) -> jint {
+    if map_handle == 0 {
+        return TRY_PUT_INVALID_HANDLE;
+    }
+    // The value is converted only if the key is, as no JNI calls are allowed while
+    // an exception is pending.
+    let key = match env.convert_byte_array(key) {
+        Ok(key) => key,
+        Err(_) => {
+            let _ = env.exception_clear();
+            return TRY_PUT_CONVERSION_ERROR;
+        }
+    };
+    let value = match env.convert_byte_array(value) {
+        Ok(value) => value,
+        Err(_) => {
+            let _ = env.exception_clear();
+            return TRY_PUT_CONVERSION_ERROR;
+        }
+    };
+    if is_key_too_long(&key) {
+        return TRY_PUT_KEY_TOO_LONG;
+    }
+    // The resource manager panics on the handles of wrong types, so the handle is checked
+    // separately to tell such panics from the ones of the storage.
+    let valid_handle = panic::catch_unwind(|| {
+        utils::cast_handle::<IndexHandle>(map_handle);
+    }).is_ok();
+    if !valid_handle {
+        return TRY_PUT_INVALID_HANDLE;
+    }
+    let res = panic::catch_unwind(|| {
+        utils::measure(Operation::Put, || {
+            match **utils::cast_handle::<IndexHandle>(map_handle) {
+                IndexType::SnapshotIndex(_) => TRY_PUT_SNAPSHOT,
+                IndexType::ForkIndex(ref mut map, ref mut size) => {
+                    on_put(map, size, &key);
+                    map.put(&key, value);
+                    TRY_PUT_OK
+                }
+            }
+        })
+    });
+    res.unwrap_or(TRY_PUT_STORAGE_ERROR)
+}
+
+/// Sets `value` identified by the `i64` key into the index.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativePutByLongKey(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jlong,
+    value: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map, ref mut size) => {
+            let key = utils::convert_long_key(key);
+            let value = env.convert_byte_array(value)?;
+            on_put(map, size, &key);
+            map.put(&key, value);
+            Ok(())
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Sets `value` identified by the `key` into the index and returns the number of entries
+/// in the index.
+///
+/// The first call counts the entries, which takes O(n) time. The count is cached in the
+/// index object and adjusted by the subsequent modifications made through it, which costs
+/// an additional lookup per `put` or `remove`. The modifications of the same index made
+/// through other objects, e.g., other `MapIndexProxy` instances or the write batches,
+/// are not reflected, so the count is accurate only if all the modifications of the index
+/// in this fork are made through this object. The entries are counted again after the fork
+/// is rolled back to a savepoint or replays operations with `Fork.nativeReplayOps`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativePutReturningSize(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+    value: jbyteArray,
+) -> jlong {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map, ref mut size) => {
+            let key = env.convert_byte_array(key)?;
+            let value = env.convert_byte_array(value)?;
+            check_key_length(&env, &key)?;
+            let mut count = match size.count() {
+                Some(count) => *count,
+                None => map.keys().count() as u64,
+            };
+            if !map.contains(&key) {
+                count += 1;
+            }
+            map.put(&key, value);
+            size.set(count);
+            Ok(count as jlong)
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Combines `value` with the value identified by the `key` using the given merge operation,
+/// and writes the result into the index.
+///
+/// Supported operations:
+/// - `0` — overwrites the current value with `value`;
+/// - `1` — appends `value` bytes to the current value;
+/// - `2` — adds `value` to the current value, both treated as little-endian `i64`.
+///
+/// An absent value is treated as an empty array, or as zero for the numeric addition.
+///
+/// The index is not modified if the merge fails: `IllegalArgumentException` is thrown
+/// if the operation is unknown, or `value` or the current value, naming the key,
+/// is not an 8-byte integer for the numeric addition, and `ArithmeticException`
+/// if the addition overflows.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeMergeOp(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+    value: jbyteArray,
+    op: jint,
+) {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map, ref mut size) => {
+            let key = env.convert_byte_array(key)?;
+            let value = env.convert_byte_array(value)?;
+            check_key_length(&env, &key)?;
+            let merged = merge_values(&env, &key, map.get(&key), value, op)?;
+            on_put(map, size, &key);
+            map.put(&key, merged);
+            Ok(())
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns value identified by the `key`. If value is not found, it is obtained from
+/// the `get` method of the given `java.util.function.Supplier<byte[]>`, set into the index
+/// and returned.
+///
+/// An exception thrown by the supplier is propagated to the caller, and the index
+/// is not modified.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeGetOrCompute(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+    supplier: JObject,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map, ref mut size) => {
+            let key = env.convert_byte_array(key)?;
+            if let Some(val) = map.get(&key) {
+                return utils::convert_bytes(&env, &val);
+            }
+            check_key_length(&env, &key)?;
+            let computed = env
+                .call_method(supplier, "get", "()Ljava/lang/Object;", &[])?
+                .l()?;
+            assert!(!computed.is_null(), "The supplier returned null");
+            let value = env.convert_byte_array(computed.into_inner())?;
+            on_put(map, size, &key);
+            map.put(&key, value);
+            Ok(computed.into_inner())
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Sets `new_value` identified by the `key` into the index only if the current value
+/// is equal to `expected`, or if there is no current value and `expected` is null.
+/// Returns `true` if the value was set.
+///
+/// Throws `NullPointerException` if `new_value` is null.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCompareAndSet(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+    expected: jbyteArray,
+    new_value: jbyteArray,
+) -> jboolean {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map, ref mut size) => {
+            check_not_null(&env, new_value, "new value")?;
+            let key = env.convert_byte_array(key)?;
+            let expected = if expected.is_null() {
+                None
+            } else {
+                Some(env.convert_byte_array(expected)?)
+            };
+            if map.get(&key) != expected {
+                return Ok(false as jboolean);
+            }
+            let new_value = env.convert_byte_array(new_value)?;
+            check_key_length(&env, &key)?;
+            on_put(map, size, &key);
+            map.put(&key, new_value);
+            Ok(true as jboolean)
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Removes value identified by the `key` from the index.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeRemove(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| {
+        utils::measure(Operation::Remove, || {
+            match **utils::cast_handle::<IndexHandle>(map_handle) {
+                IndexType::SnapshotIndex(_) => {
+                    panic!("Unable to modify snapshot.");
+                }
+                IndexType::ForkIndex(ref mut map, ref mut size) => {
+                    let key = env.convert_byte_array(key)?;
+                    on_remove(map, size, &key);
+                    map.remove(&key);
+                    Ok(())
+                }
+            }
+        })
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Removes the values identified by the keys in the range `[from, to)` from the index
+/// and returns the number of removed values.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeRemoveRange(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    from: jbyteArray,
+    to: jbyteArray,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        match **utils::cast_handle::<IndexHandle>(map_handle) {
+            IndexType::SnapshotIndex(_) => {
+                panic!("Unable to modify snapshot.");
+            }
+            IndexType::ForkIndex(ref mut map, ref mut size) => {
+                let from = env.convert_byte_array(from)?;
+                let to = env.convert_byte_array(to)?;
+                // The keys are collected first, as the index cannot be modified
+                // during the iteration.
+                let keys: Vec<Key> = map.keys_from(&from).take_while(|key| *key < to).collect();
+                for key in &keys {
+                    map.remove(key);
+                }
+                if let Some(count) = size.count() {
+                    *count -= keys.len() as u64;
+                }
+                Ok(keys.len() as jlong)
+            }
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Exchanges the values identified by `key_a` and `key_b` in the index.
+///
+/// If only one of the keys has a value, the value is moved to the other key and the key
+/// it was identified by is removed. If neither key has a value, the index is not changed.
+/// The number of entries in the index never changes.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeSwap(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key_a: jbyteArray,
+    key_b: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map, _) => {
+            let key_a = env.convert_byte_array(key_a)?;
+            let key_b = env.convert_byte_array(key_b)?;
+            match (map.get(&key_a), map.get(&key_b)) {
+                (Some(value_a), Some(value_b)) => {
+                    map.put(&key_a, value_b);
+                    map.put(&key_b, value_a);
+                }
+                (Some(value_a), None) => {
+                    map.remove(&key_a);
+                    map.put(&key_b, value_a);
+                }
+                (None, Some(value_b)) => {
+                    map.remove(&key_b);
+                    map.put(&key_a, value_b);
+                }
+                (None, None) => {}
+            }
+            Ok(())
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Moves the value identified by `old_key` to `new_key`, overwriting the value of `new_key`
+/// if any, and removes `old_key`. Returns `true` if the value was moved, and `false`
+/// if `old_key` has no value, in which case the index is not changed.
+///
+/// If the keys are equal, the index is not changed, and `true` is returned if the key
+/// has a value.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeMoveEntry(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    old_key: jbyteArray,
+    new_key: jbyteArray,
+) -> jboolean {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map, ref mut size) => {
+            let old_key = env.convert_byte_array(old_key)?;
+            let new_key = env.convert_byte_array(new_key)?;
+            check_key_length(&env, &new_key)?;
+            let moved = match map.get(&old_key) {
+                Some(_) if old_key == new_key => true,
+                Some(value) => {
+                    on_put(map, size, &new_key);
+                    map.put(&new_key, value);
+                    on_remove(map, size, &old_key);
+                    map.remove(&old_key);
+                    true
+                }
+                None => false,
+            };
+            Ok(moved as jboolean)
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Adds `delta` to the value of the key, treated as a little-endian `i64`, and returns
+/// the new value. An absent value is treated as zero.
+///
+/// Throws `IllegalArgumentException` if the stored value is not an 8-byte integer,
+/// and `ArithmeticException` on overflow; the index is not changed in both cases.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeIncrement(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+    delta: jlong,
+) -> jlong {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map, ref mut size) => {
+            let key = env.convert_byte_array(key)?;
+            check_key_length(&env, &key)?;
+            let current = match map.get(&key) {
+                Some(value) => match utils::convert_to_i64(&value) {
+                    Some(current) => current,
+                    None => {
+                        let message =
+                            format!("The value is not an 8-byte integer: {} bytes", value.len());
+                        return utils::throw_and_fail(&env, ILLEGAL_ARGUMENT_EXCEPTION, message);
+                    }
+                },
+                None => 0,
+            };
+            let new_value = match current.checked_add(delta) {
+                Some(new_value) => new_value,
+                None => {
+                    return utils::throw_and_fail(&env, ARITHMETIC_EXCEPTION, "Integer overflow");
+                }
+            };
+            on_put(map, size, &key);
+            map.put(&key, utils::convert_i64(new_value));
+            Ok(new_value)
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Clears the index, removing all values.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeClear(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map, ref mut size) => {
+            map.clear();
+            size.set(0);
+            Ok(())
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Replaces the content of the index with the given entries: clears the index and puts
+/// `values[i]` identified by `keys[i]` for each `i`.
+///
+/// The arrays are converted and their lengths are checked before the index is modified.
+/// The replacement is atomic within the fork: other views see either the old or the new
+/// content once the fork is merged.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeReplaceAll(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    keys: jobjectArray,
+    values: jobjectArray,
+) {
+    let res = panic::catch_unwind(|| match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map, ref mut size) => {
+            let keys = utils::convert_to_byte_arrays(&env, keys)?;
+            let values = utils::convert_to_byte_arrays(&env, values)?;
+            for key in &keys {
+                check_key_length(&env, key)?;
+            }
+            assert_eq!(
+                keys.len(),
+                values.len(),
+                "Keys and values must have the same length"
+            );
+            map.clear();
+            size.invalidate();
+            for (key, value) in keys.into_iter().zip(values) {
+                map.put(&key, value);
+            }
+            Ok(())
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Copies all the entries of the index into the `MapIndex` with the name `dst_name`
+/// in the given fork. If `clear_source` is `true`, the source index is cleared afterwards,
+/// which allows to rename an index.
+///
+/// Panics if the view is a snapshot, or if `clear_source` is `true` for an index
+/// created from a snapshot.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCopyInto(
+    env: JNIEnv,
+    _: JObject,
+    src_handle: Handle,
+    dst_name: JString,
+    view_handle: Handle,
+    clear_source: jboolean,
+) {
+    let res = panic::catch_unwind(|| {
+        let dst_name = utils::convert_to_string(&env, dst_name)?;
+        // The entries are collected first, as the source may belong to the same fork
+        // as the destination.
+        let entries: Vec<(Key, Value)> = match **utils::cast_handle::<IndexHandle>(src_handle) {
+            IndexType::SnapshotIndex(ref map) => map.iter().collect(),
+            IndexType::ForkIndex(ref map, _) => map.iter().collect(),
+        };
+        {
+            let fork = match *utils::cast_handle::<View>(view_handle).get() {
+                ViewRef::Snapshot(_) => panic!("Unable to modify snapshot."),
+                ViewRef::Fork(ref mut fork) => fork,
+            };
+            let mut dst: Index<&mut Fork> = Index::new(dst_name, fork);
+            for (key, value) in entries {
+                dst.put(&key, value);
+            }
+        }
+        if clear_source == JNI_TRUE {
+            match **utils::cast_handle::<IndexHandle>(src_handle) {
+                IndexType::SnapshotIndex(_) => panic!("Unable to modify snapshot."),
+                IndexType::ForkIndex(ref mut map, ref mut size) => {
+                    map.clear();
+                    size.set(0);
+                }
+            }
+        }
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Puts every entry of the `MapIndex` with the given `name` in the source view into
+/// the destination index, overwriting the values of the existing keys. Allows to seed
+/// an index of a fork with the contents of the same index from a known snapshot.
+///
+/// Panics if the destination index is created from a snapshot.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeCopyIndexFromView(
+    env: JNIEnv,
+    _: JObject,
+    dst_handle: Handle,
+    src_view_handle: Handle,
+    name: JString,
+) {
+    let res = panic::catch_unwind(|| {
+        let name = utils::convert_to_string(&env, name)?;
+        // The entries are collected first, as the source view may be the fork
+        // of the destination.
+        let entries: Vec<(Key, Value)> = match *utils::cast_handle::<View>(src_view_handle).get() {
+            ViewRef::Snapshot(snapshot) => Index::new(name, &*snapshot).iter().collect(),
+            ViewRef::Fork(ref fork) => Index::new(name, &**fork).iter().collect(),
+        };
+        match **utils::cast_handle::<IndexHandle>(dst_handle) {
+            IndexType::SnapshotIndex(_) => panic!("Unable to modify snapshot."),
+            IndexType::ForkIndex(ref mut map, ref mut size) => {
+                for (key, value) in entries {
+                    on_put(map, size, &key);
+                    map.put(&key, value);
+                }
+            }
+        }
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Puts every entry of the source index into the empty `MapIndex` with the name `dst_name`
+/// in the given fork under the key returned by the `byte[] apply(byte[])` method of the mapper
+/// for the key of the entry.
+///
+/// The mapper is called for all the entries before any of them is written, so the destination
+/// is not modified if the mapper throws. Throws `NullPointerException` if the mapper returns
+/// null, and `IllegalArgumentException` if it returns the same key for two entries or a key
+/// longer than the maximum key length, or if the destination index is not empty.
+/// Panics if the view is a snapshot.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeRekey(
+    env: JNIEnv,
+    _: JObject,
+    src_handle: Handle,
+    dst_name: JString,
+    view_handle: Handle,
+    mapper: JObject,
+) {
+    let res = panic::catch_unwind(|| {
+        let dst_name = utils::convert_to_string(&env, dst_name)?;
+        let fork = match *utils::cast_handle::<View>(view_handle).get() {
+            ViewRef::Snapshot(_) => panic!("Unable to modify snapshot."),
+            ViewRef::Fork(ref mut fork) => fork,
+        };
+        let mut dst: Index<&mut Fork> = Index::new(dst_name.clone(), fork);
+        if dst.keys().next().is_some() {
+            let message = format!("The destination index {} is not empty", dst_name);
+            return utils::throw_and_fail(&env, ILLEGAL_ARGUMENT_EXCEPTION, message);
+        }
+        // The entries are collected first, as the source index may belong to the same fork.
+        let entries: Vec<(Key, Value)> = match **utils::cast_handle::<IndexHandle>(src_handle) {
+            IndexType::SnapshotIndex(ref map) => map.iter().collect(),
+            IndexType::ForkIndex(ref map, _) => map.iter().collect(),
+        };
+        let mut rekeyed = BTreeMap::new();
+        for (key, value) in entries {
+            let new_key = map_key(&env, mapper, &key)?;
+            check_key_length(&env, &new_key)?;
+            if rekeyed.contains_key(&new_key) {
+                let message = format!(
+                    "The mapper returned a duplicate key {}",
+                    utils::to_hex(&new_key)
+                );
+                return utils::throw_and_fail(&env, ILLEGAL_ARGUMENT_EXCEPTION, message);
+            }
+            rekeyed.insert(new_key, value);
+        }
+        for (key, value) in rekeyed {
+            dst.put(&key, value);
+        }
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
 }
 
-/// Return next value from the values-iterator. Returns null pointer when iteration is finished.
+/// Moves all the entries of the source index into the destination index, overwriting
+/// the values of the existing keys, and clears the source. Returns the number of moved entries.
+///
+/// Panics if either index is created from a snapshot, leaving both indices unchanged,
+/// or if the handles are the same.
 #[no_mangle]
-pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValuesIterNext(
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeDrainInto(
+    env: JNIEnv,
+    _: JObject,
+    src_handle: Handle,
+    dst_handle: Handle,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        assert_ne!(
+            src_handle, dst_handle,
+            "Unable to drain an index into itself"
+        );
+        // Both indices are checked before any of them is modified, so that the source
+        // is left intact if the destination is read-only.
+        let src = &mut **utils::cast_handle::<IndexHandle>(src_handle);
+        let dst = &mut **utils::cast_handle::<IndexHandle>(dst_handle);
+        match (src, dst) {
+            (
+                &mut IndexType::ForkIndex(ref mut src_map, ref mut src_size),
+                &mut IndexType::ForkIndex(ref mut dst_map, ref mut dst_size),
+            ) => {
+                // The entries are collected first, as the source is cleared before they are put.
+                let entries: Vec<(Key, Value)> = src_map.iter().collect();
+                src_map.clear();
+                src_size.set(0);
+                let moved = entries.len() as jlong;
+                for (key, value) in entries {
+                    on_put(dst_map, dst_size, &key);
+                    dst_map.put(&key, value);
+                }
+                Ok(moved)
+            }
+            _ => panic!("Unable to modify snapshot."),
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the next value from the iterator. Returns null pointer when iteration is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeEntriesIterNext(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) -> jobject {
+    let res = panic::catch_unwind(|| {
+        utils::measure(Operation::IterNext, || {
+            let iterWrapper = &mut **utils::cast_handle::<Iter>(iter_handle);
+            match iterWrapper.next() {
+                Some(val) => new_entry(
+                    &env,
+                    &iterWrapper.pairs.element_class,
+                    iterWrapper.pairs.constructor_id,
+                    &val,
+                ),
+                None => Ok(ptr::null_mut()),
+            }
+        })
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the key of the next entry from the iterator, advancing it as
+/// `nativeEntriesIterNext` does. Returns null pointer when iteration is finished.
+///
+/// The value of the entry is read from the storage anyway, but no Java objects are created
+/// for it, so the calls can be mixed with `nativeEntriesIterNext` when the values of some
+/// entries are not needed.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeEntriesIterNextKeyOnly(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        utils::measure(Operation::IterNext, || {
+            match utils::cast_handle::<Iter>(iter_handle).next() {
+                Some((key, _)) => utils::convert_bytes(&env, &key),
+                None => Ok(ptr::null_mut()),
+            }
+        })
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the next value from the iterator without advancing it, so that the following call
+/// to `nativeEntriesIterNext` returns the same value. Returns null pointer when iteration
+/// is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeEntriesIterPeek(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) -> jobject {
+    let res = panic::catch_unwind(|| {
+        let iterWrapper = &mut **utils::cast_handle::<Iter>(iter_handle);
+        match iterWrapper.pairs.iter.peek() {
+            Some(val) => new_entry(
+                &env,
+                &iterWrapper.pairs.element_class,
+                iterWrapper.pairs.constructor_id,
+                val,
+            ),
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the cursor of the entries-iterator: the key of the last returned entry, which can
+/// be passed to `nativeCreateIterAfter` to resume the iteration. For the iterators created
+/// with `nativeCreateIterAfter` it is the given key until the next entry is returned.
+/// Returns null pointer if no entries were returned yet.
+///
+/// Peeking with `nativeEntriesIterPeek` does not change the cursor, while skipping
+/// with `nativeEntriesIterSkip` does.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeIterCursor(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| match utils::cast_handle::<Iter>(iter_handle).last_key {
+        Some(ref key) => env.byte_array_from_slice(key),
+        None => Ok(ptr::null_mut()),
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Advances the entries-iterator by `n` elements. Returns the number of skipped elements,
+/// which is less than `n` if the iteration is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeEntriesIterSkip(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+    n: jlong,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        let iter = utils::cast_handle::<Iter>(iter_handle);
+        Ok(skip(iter, n))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Destroys the underlying `MapIndex` iterator object and frees memory.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeEntriesIterFree(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) {
+    utils::drop_handle::<Iter>(&env, iter_handle);
+}
+
+/// Returns the next value from the keys-iterator. Returns null pointer when iteration is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysIterNext(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        utils::measure(Operation::IterNext, || {
+            let iter = utils::cast_handle::<Keys>(iter_handle);
+            match iter.next() {
+                Some(val) => utils::convert_bytes(&env, &val),
+                None => Ok(ptr::null_mut()),
+            }
+        })
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Rewinds the keys-iterator to its first key, so that the same handle can be used for several
+/// passes. The new pass keeps the order and the start key the iterator was created with.
+///
+/// `map_handle` must be the map the iterator was created from, otherwise
+/// `IllegalArgumentException` is thrown. The iterator is rebuilt from the current state
+/// of the map: if the map belongs to a fork, the new pass reflects the modifications made
+/// since the iterator was created.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysIterReset(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+    map_handle: Handle,
+) {
+    let res = panic::catch_unwind(|| {
+        let iter = utils::cast_handle::<Keys>(iter_handle);
+        if iter.origin.map_handle != map_handle {
+            return utils::throw_and_fail(
+                &env,
+                ILLEGAL_ARGUMENT_EXCEPTION,
+                "The iterator is not created from the given map",
+            );
+        }
+        let keys = map_keys(utils::cast_handle::<IndexHandle>(map_handle), &iter.origin);
+        iter.keys = keys;
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Advances the keys-iterator by `n` elements. Returns the number of skipped elements,
+/// which is less than `n` if the iteration is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysIterSkip(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+    n: jlong,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        let iter = utils::cast_handle::<Keys>(iter_handle);
+        Ok(skip(iter, n))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Destroys the underlying `MapIndex` keys-iterator object and frees memory.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeKeysIterFree(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) {
+    utils::drop_handle::<Keys>(&env, iter_handle);
+}
+
+/// Returns the next key accepted by the predicate of the filtering iterator. Returns null
+/// pointer when iteration is finished.
+///
+/// If the predicate throws, the exception is propagated and the iteration is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFilterKeysNext(
     env: JNIEnv,
     _: JObject,
     iter_handle: Handle,
 ) -> jbyteArray {
     let res = panic::catch_unwind(|| {
-        let iter = utils::cast_handle::<MapIndexValues<Value>>(iter_handle);
-        match iter.next() {
-            Some(val) => env.byte_array_from_slice(&val),
+        let iter = utils::cast_handle::<FilteredKeys>(iter_handle);
+        match iter.next_key(&env)? {
+            Some(key) => utils::convert_bytes(&env, &key),
             None => Ok(ptr::null_mut()),
         }
     });
     utils::unwrap_exc_or(&env, res, ptr::null_mut())
 }
 
+/// Destroys the underlying filtering keys-iterator object and frees memory.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeFilterKeysFree(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) {
+    utils::drop_handle::<FilteredKeys>(&env, iter_handle);
+}
+
+/// Return next value from the values-iterator. Returns null pointer when iteration is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValuesIterNext(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        utils::measure(Operation::IterNext, || {
+            let iter = utils::cast_handle::<Values>(iter_handle);
+            match iter.next() {
+                Some(val) => utils::convert_bytes(&env, &val),
+                None => Ok(ptr::null_mut()),
+            }
+        })
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Advances the values-iterator by `n` elements. Returns the number of skipped elements,
+/// which is less than `n` if the iteration is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValuesIterSkip(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+    n: jlong,
+) -> jlong {
+    let res = panic::catch_unwind(|| {
+        let iter = utils::cast_handle::<Values>(iter_handle);
+        Ok(skip(iter, n))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
 /// Destroys the underlying `MapIndex` values-iterator object and frees memory.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValuesIterFree(
@@ -396,5 +2692,542 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nat
     _: JObject,
     iter_handle: Handle,
 ) {
-    utils::drop_handle::<MapIndexValues<Value>>(&env, iter_handle);
+    utils::drop_handle::<Values>(&env, iter_handle);
+}
+
+/// Returns the next value from the iterator created by `nativeValuesBetween`.
+/// Returns null pointer when iteration is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValuesBetweenIterNext(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        utils::measure(Operation::IterNext, || {
+            let iter = utils::cast_handle::<ValuesInRange>(iter_handle);
+            match iter.next() {
+                Some(val) => utils::convert_bytes(&env, &val),
+                None => Ok(ptr::null_mut()),
+            }
+        })
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Destroys the iterator created by `nativeValuesBetween`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_MapIndexProxy_nativeValuesBetweenIterFree(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) {
+    utils::drop_handle::<ValuesInRange>(&env, iter_handle);
+}
+
+/// Merges `value` into the current value of the `key` with the operation, see `nativeMergeOp`.
+fn merge_values(
+    env: &JNIEnv,
+    key: &[u8],
+    current: Option<Value>,
+    value: Value,
+    op: jint,
+) -> JniResult<Value> {
+    match op {
+        MERGE_OP_OVERWRITE => Ok(value),
+        MERGE_OP_APPEND => {
+            let mut current = current.unwrap_or_default();
+            current.extend_from_slice(&value);
+            Ok(current)
+        }
+        MERGE_OP_ADD_I64 => {
+            let value = match utils::convert_to_i64(&value) {
+                Some(value) => value,
+                None => {
+                    let message =
+                        format!("The value is not an 8-byte integer: {} bytes", value.len());
+                    return utils::throw_and_fail(env, ILLEGAL_ARGUMENT_EXCEPTION, message);
+                }
+            };
+            let current = match current {
+                Some(current) => match utils::convert_to_i64(&current) {
+                    Some(current) => current,
+                    None => {
+                        let message = format!(
+                            "The value for the key {} is not an 8-byte integer: {} bytes",
+                            utils::to_hex(key),
+                            current.len()
+                        );
+                        return utils::throw_and_fail(env, ILLEGAL_ARGUMENT_EXCEPTION, message);
+                    }
+                },
+                None => 0,
+            };
+            match current.checked_add(value) {
+                Some(sum) => Ok(utils::convert_i64(sum)),
+                None => utils::throw_and_fail(env, ARITHMETIC_EXCEPTION, "Integer overflow"),
+            }
+        }
+        _ => {
+            let message = format!("Unknown merge operation: {}", op);
+            utils::throw_and_fail(env, ILLEGAL_ARGUMENT_EXCEPTION, message)
+        }
+    }
+}
+
+/// Creates an iterator element object holding the given key and value.
+fn new_entry(
+    env: &JNIEnv,
+    class: &GlobalRef,
+    constructor_id: JMethodID<'static>,
+    entry: &(Key, Value),
+) -> JniResult<jobject> {
+    let key: JObject = utils::convert_bytes(env, &entry.0)?.into();
+    let value: JObject = utils::convert_bytes(env, &entry.1)?.into();
+    Ok(env
+        .new_object_by_id(class, constructor_id, &[key.into(), value.into()])?
+        .into_inner())
+}
+
+/// Creates a keys-iterator of the map identified by the origin.
+fn create_keys_iter(origin: KeysOrigin) -> Keys<'static> {
+    let map = utils::cast_handle::<IndexHandle>(origin.map_handle);
+    let keys = map_keys(map, &origin);
+    Keys::new(KeysIter { keys, origin }, map.guard())
+}
+
+/// Returns the keys of the map in the order and starting at the key of the origin.
+fn map_keys(map: &'static IndexHandle, origin: &KeysOrigin) -> Box<Iterator<Item = Key>> {
+    let from = origin.from.clone().unwrap_or_default();
+    let keys = match **map {
+        IndexType::SnapshotIndex(ref map) => map.keys_from(&from),
+        IndexType::ForkIndex(ref map, _) => map.keys_from(&from),
+    };
+    if origin.reverse {
+        // The storage iterators are not double-ended, so the keys are buffered.
+        let keys: Vec<Key> = keys.collect();
+        Box::new(keys.into_iter().rev())
+    } else {
+        Box::new(keys)
+    }
+}
+
+/// Advances the iterator by `n` elements, returning the number of skipped ones.
+fn skip<I: Iterator>(iter: &mut I, n: jlong) -> jlong {
+    assert!(n >= 0, "Negative number of elements to skip: {}", n);
+    let mut skipped = 0;
+    while skipped < n && iter.next().is_some() {
+        skipped += 1;
+    }
+    skipped
+}
+
+/// Packs the presence of each key into a bitmap, one bit per key, least significant bit first.
+fn presence_bitmap<F: Fn(&Key) -> bool>(keys: &[Key], contains: F) -> Vec<u8> {
+    let mut bitmap = vec![0u8; (keys.len() + 7) / 8];
+    for (i, key) in keys.iter().enumerate() {
+        if contains(key) {
+            bitmap[i / 8] |= 1 << (i % 8);
+        }
+    }
+    bitmap
+}
+
+/// Sums the lengths of the keys and values of the entries and the per-entry overhead.
+fn approx_size_bytes<I: Iterator<Item = (Key, Value)>>(entries: I) -> jlong {
+    entries.fold(0, |size, (key, value)| {
+        size + (key.len() + value.len()) as jlong + ENTRY_OVERHEAD_BYTES
+    })
+}
+
+/// Counts the values in each of the buckets delimited by the ascending boundaries,
+/// see `nativeValueSizeHistogram`.
+fn value_size_histogram<I: Iterator<Item = Value>>(values: I, boundaries: &[jlong]) -> Vec<jlong> {
+    let mut counts = vec![0; boundaries.len() + 1];
+    for value in values {
+        let len = value.len() as jlong;
+        let bucket = match boundaries.binary_search(&len) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        };
+        counts[bucket] += 1;
+    }
+    counts
+}
+
+/// The stats of a map, see `nativeGetStats`.
+struct IndexStats {
+    size: jlong,
+    approx_size_bytes: jlong,
+    first_key: Option<Key>,
+    last_key: Option<Key>,
+}
+
+/// Collects the stats of the entries in a single pass.
+fn index_stats<I: Iterator<Item = (Key, Value)>>(entries: I) -> IndexStats {
+    let mut stats = IndexStats {
+        size: 0,
+        approx_size_bytes: 0,
+        first_key: None,
+        last_key: None,
+    };
+    for (key, value) in entries {
+        stats.size += 1;
+        stats.approx_size_bytes += (key.len() + value.len()) as jlong + ENTRY_OVERHEAD_BYTES;
+        if stats.first_key.is_none() {
+            stats.first_key = Some(key.clone());
+        }
+        stats.last_key = Some(key);
+    }
+    stats
+}
+
+/// Folds the entries into a hash: each step hashes the previous hash followed
+/// by the key and value frames, starting with the zero hash.
+fn content_hash<I: Iterator<Item = (Key, Value)>>(entries: I) -> Hash {
+    let mut hash = Hash::zero();
+    let mut buffer = Vec::new();
+    for (key, value) in entries {
+        buffer.clear();
+        buffer.extend_from_slice(hash.as_ref());
+        utils::write_frame(&mut buffer, &key);
+        utils::write_frame(&mut buffer, &value);
+        hash = crypto::hash(&buffer);
+    }
+    hash
+}
+
+/// An error of `reduce_i64`.
+enum ReduceError {
+    /// The value of the key is not an 8-byte integer, but has the given length.
+    NotAnInteger(Key, usize),
+    Overflow,
+}
+
+/// Folds the values treated as little-endian `i64` with the operation, see
+/// `nativeReduceValuesI64`.
+fn reduce_i64<I: Iterator<Item = (Key, Value)>>(
+    entries: I,
+    op: jint,
+    initial: i64,
+) -> Result<i64, ReduceError> {
+    let fold: fn(i64, i64) -> Option<i64> = match op {
+        REDUCE_OP_ADD => i64::checked_add,
+        REDUCE_OP_MIN => |acc, value| Some(acc.min(value)),
+        REDUCE_OP_MAX => |acc, value| Some(acc.max(value)),
+        REDUCE_OP_MUL => i64::checked_mul,
+        _ => panic!("Unknown reduce operation: {}", op),
+    };
+    let mut acc = initial;
+    for (key, value) in entries {
+        let value = match utils::convert_to_i64(&value) {
+            Some(value) => value,
+            None => return Err(ReduceError::NotAnInteger(key, value.len())),
+        };
+        acc = fold(acc, value).ok_or(ReduceError::Overflow)?;
+    }
+    Ok(acc)
+}
+
+/// Folds the values of the map with `reduce_i64`, throwing `IllegalArgumentException` naming
+/// the key if a value is not an 8-byte integer, and `ArithmeticException` on overflow.
+fn reduce_map_i64(env: &JNIEnv, map: &IndexType, op: jint, initial: i64) -> JniResult<i64> {
+    let result = match *map {
+        IndexType::SnapshotIndex(ref map) => reduce_i64(map.iter(), op, initial),
+        IndexType::ForkIndex(ref map, _) => reduce_i64(map.iter(), op, initial),
+    };
+    match result {
+        Ok(value) => Ok(value),
+        Err(ReduceError::NotAnInteger(key, len)) => {
+            let message = format!(
+                "The value for the key {} is not an 8-byte integer: {} bytes",
+                utils::to_hex(&key),
+                len
+            );
+            utils::throw_and_fail(env, ILLEGAL_ARGUMENT_EXCEPTION, message)
+        }
+        Err(ReduceError::Overflow) => {
+            utils::throw_and_fail(env, ARITHMETIC_EXCEPTION, "Integer overflow")
+        }
+    }
+}
+
+/// Throws `NoSuchElementException` if the map is empty.
+fn check_map_not_empty(env: &JNIEnv, map: &IndexType) -> JniResult<()> {
+    let is_empty = match *map {
+        IndexType::SnapshotIndex(ref map) => map.keys().next().is_none(),
+        IndexType::ForkIndex(ref map, _) => map.keys().next().is_none(),
+    };
+    if is_empty {
+        return utils::throw_and_fail(env, NO_SUCH_ELEMENT_EXCEPTION, "The map is empty");
+    }
+    Ok(())
+}
+
+/// Parses the entries packed as pairs of key and value frames. Returns `None` if the data
+/// is truncated.
+fn parse_packed_entries(data: &[u8]) -> Option<Vec<(Key, Value)>> {
+    let mut reader = utils::FrameReader::new(data);
+    let mut entries = Vec::new();
+    while !reader.is_empty() {
+        let key = reader.read_frame()?;
+        let value = reader.read_frame()?;
+        entries.push((key.to_vec(), value.to_vec()));
+    }
+    Some(entries)
+}
+
+/// Adjusts the cached number of entries, if any, before the `key` is put into the index.
+#[cfg_attr(feature = "cargo-clippy", allow(ptr_arg))]
+fn on_put(map: &Index<&'static mut Fork>, size: &mut CachedSize, key: &Key) {
+    if let Some(count) = size.count() {
+        if !map.contains(key) {
+            *count += 1;
+        }
+    }
+}
+
+/// Returns `true` if the key is longer than the maximum key length.
+fn is_key_too_long(key: &[u8]) -> bool {
+    let max_len = MAX_KEY_LENGTH.load(Ordering::Relaxed);
+    max_len != 0 && key.len() > max_len
+}
+
+/// Throws `NullPointerException` if the array is null.
+fn check_not_null(env: &JNIEnv, array: jbyteArray, name: &str) -> JniResult<()> {
+    if array.is_null() {
+        let message = format!("The {} must not be null", name);
+        return utils::throw_and_fail(env, NULL_POINTER_EXCEPTION, message);
+    }
+    Ok(())
+}
+
+/// Throws `IllegalArgumentException` if the key is longer than the maximum key length.
+fn check_key_length(env: &JNIEnv, key: &[u8]) -> JniResult<()> {
+    if is_key_too_long(key) {
+        let message = format!(
+            "The key length ({} bytes) exceeds the maximum of {} bytes",
+            key.len(),
+            MAX_KEY_LENGTH.load(Ordering::Relaxed)
+        );
+        return utils::throw_and_fail(env, ILLEGAL_ARGUMENT_EXCEPTION, message);
+    }
+    Ok(())
+}
+
+/// Adjusts the cached number of entries, if any, before the `key` is removed from the index.
+#[cfg_attr(feature = "cargo-clippy", allow(ptr_arg))]
+fn on_remove(map: &Index<&'static mut Fork>, size: &mut CachedSize, key: &Key) {
+    if let Some(count) = size.count() {
+        if map.contains(key) {
+            *count -= 1;
+        }
+    }
+}
+
+/// Creates an array of `MapEntryInternal` with the entries of the map, the number of which
+/// is counted first to allocate the array. Throws `IllegalArgumentException` if the map
+/// has more than `max_entries` entries.
+fn to_entries_array<T: AsRef<Snapshot>>(
+    env: &JNIEnv,
+    map: &Index<T>,
+    max_entries: usize,
+) -> JniResult<jobjectArray> {
+    let count = map.iter().take(max_entries + 1).count();
+    if count > max_entries {
+        let message = format!("The map has more than {} entries", max_entries);
+        return utils::throw_and_fail(env, ILLEGAL_ARGUMENT_EXCEPTION, message);
+    }
+    new_entries_array(env, count, map.iter().take(count))
+}
+
+/// Converts the maximum number of entries, throwing `IllegalArgumentException` if it is negative.
+fn check_max_entries(env: &JNIEnv, max_entries: jint) -> JniResult<usize> {
+    if max_entries < 0 {
+        let message = format!("Negative maximum number of entries: {}", max_entries);
+        return utils::throw_and_fail(env, ILLEGAL_ARGUMENT_EXCEPTION, message);
+    }
+    Ok(max_entries as usize)
+}
+
+/// Creates an array of `MapEntryInternal` with `count` entries taken from the iterator.
+fn new_entries_array<I: Iterator<Item = (Key, Value)>>(
+    env: &JNIEnv,
+    count: usize,
+    iter: I,
+) -> JniResult<jobjectArray> {
+    let entries = env.new_object_array(count as jsize, JAVA_ENTRY_FQN, JObject::null())?;
+    for (i, (key, value)) in iter.take(count).enumerate() {
+        env.with_local_frame(4, || {
+            let key: JObject = utils::convert_bytes(env, &key)?.into();
+            let value: JObject = utils::convert_bytes(env, &value)?.into();
+            let entry = env.new_object(JAVA_ENTRY_FQN, "([B[B)V", &[key.into(), value.into()])?;
+            env.set_object_array_element(entries, i as jsize, entry)?;
+            Ok(JObject::null())
+        })?;
+    }
+    Ok(entries)
+}
+
+/// Creates an array of `MapEntryInternal` with the first and the last entries of the map.
+fn first_and_last_entries<T: AsRef<Snapshot>>(
+    env: &JNIEnv,
+    map: &Index<T>,
+) -> JniResult<jobjectArray> {
+    let entries = env.new_object_array(2, JAVA_ENTRY_FQN, JObject::null())?;
+    let first = map.iter().next();
+    // An empty map has no last entry, so it is looked up only if there is the first one.
+    let last = first.as_ref().and_then(|_| map.iter().last());
+    for (i, entry) in [first, last].iter().enumerate() {
+        if let Some((ref key, ref value)) = *entry {
+            let key: JObject = utils::convert_bytes(env, key)?.into();
+            let value: JObject = utils::convert_bytes(env, value)?.into();
+            let entry = env.new_object(JAVA_ENTRY_FQN, "([B[B)V", &[key.into(), value.into()])?;
+            env.set_object_array_element(entries, i as jsize, entry)?;
+        }
+    }
+    Ok(entries)
+}
+
+/// Counts the keys in the range `[from, to)`, which is unbounded if `to` is `None`.
+#[cfg_attr(feature = "cargo-clippy", allow(ptr_arg))]
+fn count_keys_between<T: AsRef<Snapshot>>(map: &Index<T>, from: &Key, to: Option<&Key>) -> usize {
+    let keys = map.keys_from(from);
+    match to {
+        Some(to) => keys.take_while(|key| key < to).count(),
+        None => keys.count(),
+    }
+}
+
+#[cfg_attr(feature = "cargo-clippy", allow(ptr_arg))]
+fn floor_key<T: AsRef<Snapshot>>(map: &Index<T>, target: &Key) -> Option<Key> {
+    map.keys().take_while(|key| key <= target).last()
+}
+
+#[cfg_attr(feature = "cargo-clippy", allow(ptr_arg))]
+fn warm_up_map(map_handle: Handle, from: &Key, to: Option<&Key>) {
+    match **utils::cast_handle::<IndexHandle>(map_handle) {
+        IndexType::SnapshotIndex(ref map) => warm_up(map, from, to),
+        IndexType::ForkIndex(ref map, _) => warm_up(map, from, to),
+    }
+}
+
+/// Reads and discards the entries in the range `[from, to)`, which is unbounded
+/// if `to` is `None`.
+#[cfg_attr(feature = "cargo-clippy", allow(ptr_arg))]
+fn warm_up<T: AsRef<Snapshot>>(map: &Index<T>, from: &Key, to: Option<&Key>) {
+    for (key, _) in map.iter_from(from) {
+        if to.map_or(false, |to| key >= *to) {
+            break;
+        }
+    }
+}
+
+/// Writes the entries to the file in the given format, see `nativeDumpToFile`.
+fn dump_to_file<I: Iterator<Item = (Key, Value)>>(
+    path: &str,
+    entries: I,
+    format: jint,
+) -> io::Result<()> {
+    // The format is checked before the file is replaced.
+    assert!(
+        format == DUMP_FORMAT_BINARY || format == DUMP_FORMAT_CSV_HEX,
+        "Unknown dump format: {}",
+        format
+    );
+    let mut out = BufWriter::new(File::create(path)?);
+    let mut buffer = Vec::new();
+    for (key, value) in entries {
+        buffer.clear();
+        match format {
+            DUMP_FORMAT_BINARY => {
+                utils::write_frame(&mut buffer, &key);
+                utils::write_frame(&mut buffer, &value);
+            }
+            DUMP_FORMAT_CSV_HEX => {
+                let line = format!("{},{}\n", utils::to_hex(&key), utils::to_hex(&value));
+                buffer.extend_from_slice(line.as_bytes());
+            }
+            _ => unreachable!(),
+        }
+        out.write_all(&buffer)?;
+    }
+    out.flush()
+}
+
+/// Returns the key returned by the `apply` method of the mapper, see `nativeRekey`.
+fn map_key(env: &JNIEnv, mapper: JObject, key: &[u8]) -> JniResult<Key> {
+    let java_key: JObject = utils::convert_bytes(env, key)?.into();
+    let new_key = env
+        .call_method(mapper, "apply", "([B)[B", &[java_key.into()])?
+        .l()?;
+    env.delete_local_ref(java_key)?;
+    if new_key.is_null() {
+        return utils::throw_and_fail(env, NULL_POINTER_EXCEPTION, "The mapper returned null");
+    }
+    let result = env.convert_byte_array(new_key.into_inner())?;
+    env.delete_local_ref(new_key)?;
+    Ok(result)
+}
+
+/// Passes the entries of the map to the `accept` method of the callback until it
+/// returns `false`.
+fn for_each<T: AsRef<Snapshot>>(env: &JNIEnv, map: &Index<T>, callback: JObject) -> JniResult<()> {
+    for (key, value) in map.iter() {
+        let key: JObject = utils::convert_bytes(env, &key)?.into();
+        let value: JObject = utils::convert_bytes(env, &value)?.into();
+        let proceed = env
+            .call_method(callback, "accept", "([B[B)Z", &[key.into(), value.into()])?
+            .z()?;
+        // The arrays are released right away, as the number of local references is limited.
+        env.delete_local_ref(key)?;
+        env.delete_local_ref(value)?;
+        if !proceed {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Threads the accumulator through the `apply` method of the accumulator function for
+/// each entry of the map, see `nativeFoldEntries`.
+fn fold_entries<'e, T: AsRef<Snapshot>>(
+    env: &JNIEnv<'e>,
+    map: &Index<T>,
+    initial: JObject<'e>,
+    accumulator: JObject,
+) -> JniResult<JObject<'e>> {
+    let mut acc = initial;
+    for (key, value) in map.iter() {
+        let key: JObject = utils::convert_bytes(env, &key)?.into();
+        let value: JObject = utils::convert_bytes(env, &value)?.into();
+        let next = env
+            .call_method(
+                accumulator,
+                "apply",
+                "(Ljava/lang/Object;[B[B)Ljava/lang/Object;",
+                &[acc.into(), key.into(), value.into()],
+            )?.l()?;
+        env.delete_local_ref(key)?;
+        env.delete_local_ref(value)?;
+        // Each result is a new local reference, so the intermediate ones are released;
+        // the initial accumulator is owned by the caller.
+        if acc.into_inner() != initial.into_inner() {
+            env.delete_local_ref(acc)?;
+        }
+        acc = next;
+    }
+    Ok(acc)
+}
+
+/// Returns a description of the `MapIndex` identified by the given handle for the diagnostic
+/// purposes, see `utils::describe_handle`.
+pub(crate) fn describe_map_index(map_handle: Handle) -> String {
+    utils::describe_handle::<IndexHandle, _>(map_handle, |index| match **index {
+        IndexType::SnapshotIndex(_) => "MapIndex of Snapshot".to_owned(),
+        IndexType::ForkIndex(_, ref size) => match size.get() {
+            Some(count) => format!("MapIndex of Fork, {} entries", count),
+            None => "MapIndex of Fork, unknown number of entries".to_owned(),
+        },
+    })
 }
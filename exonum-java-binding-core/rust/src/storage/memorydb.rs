@@ -12,14 +12,225 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use exonum::storage::{Database, MemoryDB};
+use exonum::storage::{Database, Fork, MemoryDB, Patch, Result as StorageResult};
 use jni::objects::{JClass, JObject};
+use jni::sys::{jboolean, jbyteArray, JNI_FALSE, JNI_TRUE};
 use jni::JNIEnv;
 
+use std::collections::{BTreeSet, HashMap};
 use std::panic;
+use std::ptr;
+use std::sync::{Arc, Weak};
 
-use storage::db::{View, ViewRef};
-use utils::{self, Handle};
+use storage::db::{Key, View, ViewRef};
+use utils::{self, FrameReader, Handle};
+
+const SERIALIZATION_FORMAT_VERSION: u8 = 1;
+
+/// A `MemoryDB` that keeps track of the names of the indices (column families) written
+/// through the binding, as `MemoryDB` itself does not allow to enumerate them.
+///
+/// `MemoryDB` has no conflict detection, so the database also keeps a version, which is
+/// incremented on each change, and the version at which each key was last written,
+/// see `merge_checked`. The versions of the keys are kept only while there are live forks
+/// that may conflict with them.
+pub(crate) struct MemoryDb {
+    db: MemoryDB,
+    names: BTreeSet<String>,
+    version: u64,
+    // The version of the last `clear`, which writes all the keys at once.
+    cleared_version: u64,
+    // The versions the keys were last written at, by the column family.
+    modified_versions: HashMap<String, HashMap<Key, u64>>,
+    // The base versions of the forks that may be merged with `merge_checked`.
+    fork_versions: Vec<Weak<u64>>,
+    // The oldest base version the key versions were pruned at, see `prune_versions`.
+    pruned_version: u64,
+}
+
+/// The version of a `MemoryDb` a fork was created at, see `MemoryDb::merge_checked`.
+///
+/// The database keeps weak references to the base versions of the forks, so it knows
+/// which key versions are still needed to detect conflicts.
+#[derive(Clone)]
+pub(crate) struct BaseVersion(Arc<u64>);
+
+impl BaseVersion {
+    pub fn get(&self) -> u64 {
+        *self.0
+    }
+}
+
+impl MemoryDb {
+    pub fn new() -> Self {
+        MemoryDb {
+            db: MemoryDB::new(),
+            names: BTreeSet::new(),
+            version: 0,
+            cleared_version: 0,
+            modified_versions: HashMap::new(),
+            fork_versions: Vec::new(),
+            pruned_version: 0,
+        }
+    }
+
+    /// Returns the current version of the database as the base version of a fork created
+    /// from it, which must be kept as long as the fork may be merged with `merge_checked`.
+    pub fn base_version(&mut self) -> BaseVersion {
+        let version = Arc::new(self.version);
+        self.fork_versions
+            .retain(|version| version.upgrade().is_some());
+        self.fork_versions.push(Arc::downgrade(&version));
+        BaseVersion(version)
+    }
+
+    pub fn fork(&self) -> Fork {
+        self.db.fork()
+    }
+
+    pub fn merge(&mut self, patch: Patch) -> StorageResult<()> {
+        self.names
+            .extend(patch.iter().map(|(name, _)| name.clone()));
+        self.version += 1;
+        for (name, changes) in patch.iter() {
+            let versions = self
+                .modified_versions
+                .entry(name.clone())
+                .or_insert_with(HashMap::new);
+            for (key, _) in changes.iter() {
+                versions.insert(key.clone(), self.version);
+            }
+        }
+        self.prune_versions();
+        self.db.merge(patch)
+    }
+
+    /// Merges the patch of a fork created at `base_version` if it does not conflict with
+    /// the changes merged since then, and returns whether it was merged.
+    ///
+    /// The patch conflicts if any of the keys it writes was written by a merge after
+    /// `base_version`, or the database was cleared after it. The keys the fork only read
+    /// are not tracked, so the check detects write-write conflicts only: the fork may still
+    /// have read a value that was changed after `base_version`. A patch merged twice
+    /// conflicts with itself.
+    pub fn merge_checked(
+        &mut self,
+        patch: Patch,
+        base_version: &BaseVersion,
+    ) -> StorageResult<bool> {
+        if self.has_conflicts(&patch, base_version.get()) {
+            return Ok(false);
+        }
+        self.merge(patch)?;
+        Ok(true)
+    }
+
+    fn has_conflicts(&self, patch: &Patch, base_version: u64) -> bool {
+        if self.cleared_version > base_version {
+            return true;
+        }
+        patch.iter().any(|(name, changes)| {
+            let versions = match self.modified_versions.get(name.as_str()) {
+                Some(versions) => versions,
+                None => return false,
+            };
+            changes.iter().any(|(key, _)| {
+                versions
+                    .get(key.as_slice())
+                    .map_or(false, |&version| version > base_version)
+            })
+        })
+    }
+
+    /// Forgets the versions of the keys that cannot conflict with any live fork: the ones
+    /// written at or before the oldest base version of the live forks, or all of them
+    /// if there are no such forks, as the forks created later have newer base versions.
+    fn prune_versions(&mut self) {
+        self.fork_versions
+            .retain(|version| version.upgrade().is_some());
+        let oldest = self
+            .fork_versions
+            .iter()
+            .filter_map(|version| version.upgrade())
+            .map(|version| *version)
+            .min()
+            .unwrap_or(self.version);
+        // The versions are scanned only when the oldest live fork changes.
+        if oldest <= self.pruned_version {
+            return;
+        }
+        self.pruned_version = oldest;
+        for versions in self.modified_versions.values_mut() {
+            versions.retain(|_, version| *version > oldest);
+        }
+        self.modified_versions
+            .retain(|_, versions| !versions.is_empty());
+    }
+
+    /// Removes all the data from the database by merging a patch that clears every
+    /// known column family.
+    fn clear(&mut self) -> StorageResult<()> {
+        let mut fork = self.db.fork();
+        for name in &self.names {
+            fork.remove_by_prefix(name, None);
+        }
+        self.version += 1;
+        self.cleared_version = self.version;
+        self.modified_versions.clear();
+        self.db.merge(fork.into_patch())
+    }
+
+    /// Serializes the content of the database.
+    ///
+    /// The format is a version byte followed by the column families in the order of their names.
+    /// Each column family is its name frame, the number of entries as a big-endian `u32`,
+    /// and the key and value frames of each entry in the order of keys.
+    fn serialize(&self) -> Vec<u8> {
+        let snapshot = self.db.snapshot();
+        let mut buffer = vec![SERIALIZATION_FORMAT_VERSION];
+        for name in &self.names {
+            let mut entries = Vec::new();
+            let mut iter = snapshot.iter(name, &[]);
+            while let Some((key, value)) = iter.next() {
+                entries.push((key.to_vec(), value.to_vec()));
+            }
+            utils::write_frame(&mut buffer, name.as_bytes());
+            utils::write_u32(&mut buffer, entries.len() as u32);
+            for (key, value) in entries {
+                utils::write_frame(&mut buffer, &key);
+                utils::write_frame(&mut buffer, &value);
+            }
+        }
+        buffer
+    }
+
+    /// Creates a database from the data produced by `serialize`.
+    ///
+    /// Panics if the data is malformed or has an unsupported format version.
+    fn deserialize(data: &[u8]) -> Self {
+        const TRUNCATED: &str = "Truncated database data";
+        let mut reader = FrameReader::new(data);
+        let version = reader.read_u8().expect(TRUNCATED);
+        assert_eq!(
+            SERIALIZATION_FORMAT_VERSION, version,
+            "Unsupported database format version"
+        );
+        let mut db = MemoryDb::new();
+        let mut fork = db.db.fork();
+        while !reader.is_empty() {
+            let name = reader.read_frame().expect(TRUNCATED);
+            let name = String::from_utf8(name.to_vec()).expect("Invalid index name");
+            let count = reader.read_u32().expect(TRUNCATED);
+            for _ in 0..count {
+                let key = reader.read_frame().expect(TRUNCATED);
+                let value = reader.read_frame().expect(TRUNCATED);
+                fork.put(&name, key.to_vec(), value.to_vec());
+            }
+        }
+        db.merge(fork.into_patch()).expect("Unable to merge fork");
+        db
+    }
+}
 
 /// Returns pointer to created `MemoryDB` object.
 #[no_mangle]
@@ -27,7 +238,7 @@ pub extern "system" fn Java_com_exonum_binding_storage_database_MemoryDb_nativeC
     env: JNIEnv,
     _: JClass,
 ) -> Handle {
-    let res = panic::catch_unwind(|| Ok(utils::to_handle(MemoryDB::new())));
+    let res = panic::catch_unwind(|| Ok(utils::to_handle(MemoryDb::new())));
     utils::unwrap_exc_or_default(&env, res)
 }
 
@@ -38,7 +249,7 @@ pub extern "system" fn Java_com_exonum_binding_storage_database_MemoryDb_nativeF
     _: JClass,
     db_handle: Handle,
 ) {
-    utils::drop_handle::<MemoryDB>(&env, db_handle);
+    utils::drop_handle::<MemoryDb>(&env, db_handle);
 }
 
 /// Returns pointer to created `Snapshot` object.
@@ -49,8 +260,8 @@ pub extern "system" fn Java_com_exonum_binding_storage_database_MemoryDb_nativeC
     db_handle: Handle,
 ) -> Handle {
     let res = panic::catch_unwind(|| {
-        let db = utils::cast_handle::<MemoryDB>(db_handle);
-        Ok(utils::to_handle(View::from_owned_snapshot(db.snapshot())))
+        let snapshot = utils::cast_handle::<MemoryDb>(db_handle).db.snapshot();
+        Ok(utils::to_handle(View::from_owned_snapshot(snapshot)))
     });
     utils::unwrap_exc_or_default(&env, res)
 }
@@ -63,8 +274,12 @@ pub extern "system" fn Java_com_exonum_binding_storage_database_MemoryDb_nativeC
     db_handle: Handle,
 ) -> Handle {
     let res = panic::catch_unwind(|| {
-        let db = utils::cast_handle::<MemoryDB>(db_handle);
-        Ok(utils::to_handle(View::from_owned_fork(db.fork())))
+        let db = utils::cast_handle::<MemoryDb>(db_handle);
+        let snapshot = db.db.snapshot();
+        let mut view = View::from_owned_fork(db.db.fork());
+        view.set_base_version(db.base_version());
+        view.set_base_snapshot(snapshot);
+        Ok(utils::to_handle(view))
     });
     utils::unwrap_exc_or_default(&env, res)
 }
@@ -78,7 +293,7 @@ pub extern "system" fn Java_com_exonum_binding_storage_database_MemoryDb_nativeM
     view_handle: Handle,
 ) {
     let res = panic::catch_unwind(|| {
-        let db = utils::cast_handle::<MemoryDB>(db_handle);
+        let db = utils::cast_handle::<MemoryDb>(db_handle);
         let fork = match *utils::cast_handle::<View>(view_handle).get() {
             ViewRef::Snapshot(_) => panic!("Attempt to merge snapshot instead of fork."),
             ViewRef::Fork(ref fork) => fork,
@@ -89,3 +304,199 @@ pub extern "system" fn Java_com_exonum_binding_storage_database_MemoryDb_nativeM
     });
     utils::unwrap_exc_or_default(&env, res)
 }
+
+/// Merges the given fork into the database unless it conflicts with the changes merged
+/// after the fork was created, see `MemoryDb::merge_checked` for the guarantees.
+///
+/// Returns `true` if the fork was merged, and `false` if a conflict was detected,
+/// in which case the database is not changed.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_MemoryDb_nativeMergeChecked(
+    env: JNIEnv,
+    _: JObject,
+    db_handle: Handle,
+    view_handle: Handle,
+) -> jboolean {
+    let res = panic::catch_unwind(|| {
+        let db = utils::cast_handle::<MemoryDb>(db_handle);
+        let view = utils::cast_handle::<View>(view_handle);
+        let base_version = view
+            .base_version()
+            .cloned()
+            .expect("The fork was not created by a MemoryDb");
+        let patch = match *view.get() {
+            ViewRef::Snapshot(_) => panic!("Attempt to merge snapshot instead of fork."),
+            ViewRef::Fork(ref fork) => fork.patch().clone(),
+        };
+        let merged = db
+            .merge_checked(patch, &base_version)
+            .expect("Unable to merge fork");
+        Ok(if merged { JNI_TRUE } else { JNI_FALSE })
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Removes all the data from the database.
+///
+/// The snapshots created before are not affected and remain valid, as each snapshot
+/// of `MemoryDB` owns a copy of the database state.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_MemoryDb_nativeClear(
+    env: JNIEnv,
+    _: JObject,
+    db_handle: Handle,
+) {
+    let res = panic::catch_unwind(|| {
+        let db = utils::cast_handle::<MemoryDb>(db_handle);
+        db.clear().expect("Unable to clear database");
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Serializes the content of the database into a byte array, which can be turned into
+/// a database again with `nativeDeserialize`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_MemoryDb_nativeSerialize(
+    env: JNIEnv,
+    _: JObject,
+    db_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let db = utils::cast_handle::<MemoryDb>(db_handle);
+        env.byte_array_from_slice(&db.serialize())
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns pointer to the `MemoryDB` object created from the data produced by `nativeSerialize`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_MemoryDb_nativeDeserialize(
+    env: JNIEnv,
+    _: JClass,
+    data: jbyteArray,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let data = env.convert_byte_array(data)?;
+        Ok(utils::to_handle(MemoryDb::deserialize(&data)))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum::storage::{Database, MapIndex};
+
+    use super::*;
+
+    #[test]
+    fn serialize_round_trip() {
+        let mut db = MemoryDb::new();
+        let mut fork = db.db.fork();
+        {
+            let mut first: MapIndex<_, Vec<u8>, Vec<u8>> = MapIndex::new("first", &mut fork);
+            first.put(&vec![1], vec![10]);
+            first.put(&vec![2], vec![]);
+        }
+        {
+            let mut second: MapIndex<_, Vec<u8>, Vec<u8>> = MapIndex::new("second", &mut fork);
+            second.put(&vec![3], vec![30, 31]);
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let data = db.serialize();
+        let restored = MemoryDb::deserialize(&data);
+
+        assert_eq!(db.names, restored.names);
+        assert_eq!(data, restored.serialize());
+        let snapshot = restored.db.snapshot();
+        let second: MapIndex<_, Vec<u8>, Vec<u8>> = MapIndex::new("second", &*snapshot);
+        assert_eq!(Some(vec![30, 31]), second.get(&vec![3]));
+    }
+
+    #[test]
+    fn clear() {
+        let mut db = MemoryDb::new();
+        let mut fork = db.db.fork();
+        fork.put("first", vec![1], vec![10]);
+        fork.put("second", vec![2], vec![20]);
+        db.merge(fork.into_patch()).unwrap();
+        let snapshot = db.db.snapshot();
+
+        db.clear().unwrap();
+
+        assert_eq!(None, db.db.snapshot().get("first", &[1]));
+        assert_eq!(None, db.db.snapshot().get("second", &[2]));
+        assert_eq!(Some(vec![10]), snapshot.get("first", &[1]));
+    }
+
+    #[test]
+    fn merge_checked() {
+        let mut db = MemoryDb::new();
+        let base_version = db.base_version();
+        let mut first = db.db.fork();
+        first.put("map", vec![1], vec![10]);
+        let mut second = db.db.fork();
+        second.put("map", vec![1], vec![20]);
+        let mut third = db.db.fork();
+        third.put("map", vec![2], vec![30]);
+
+        assert!(db.merge_checked(first.into_patch(), &base_version).unwrap());
+        let merged = db
+            .merge_checked(second.into_patch(), &base_version)
+            .unwrap();
+        assert!(!merged);
+        assert!(db.merge_checked(third.into_patch(), &base_version).unwrap());
+
+        let snapshot = db.db.snapshot();
+        assert_eq!(Some(vec![10]), snapshot.get("map", &[1]));
+        assert_eq!(Some(vec![30]), snapshot.get("map", &[2]));
+    }
+
+    #[test]
+    fn merge_checked_after_clear() {
+        let mut db = MemoryDb::new();
+        let base_version = db.base_version();
+        let mut fork = db.db.fork();
+        fork.put("map", vec![1], vec![10]);
+
+        db.clear().unwrap();
+
+        let merged = db
+            .merge_checked(fork.patch().clone(), &base_version)
+            .unwrap();
+        assert!(!merged);
+        let base_version = db.base_version();
+        assert!(db.merge_checked(fork.into_patch(), &base_version).unwrap());
+    }
+
+    #[test]
+    fn prunes_versions_without_live_forks() {
+        let mut db = MemoryDb::new();
+        let old_version = db.base_version();
+        let mut fork = db.db.fork();
+        fork.put("map", vec![1], vec![10]);
+        db.merge(fork.into_patch()).unwrap();
+        assert!(db.modified_versions.contains_key("map"));
+
+        drop(old_version);
+        let mut fork = db.db.fork();
+        fork.put("map", vec![2], vec![20]);
+        db.merge(fork.into_patch()).unwrap();
+        assert!(db.modified_versions.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported database format version")]
+    fn deserialize_unknown_version() {
+        MemoryDb::deserialize(&[SERIALIZATION_FORMAT_VERSION + 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Truncated database data")]
+    fn deserialize_truncated() {
+        let mut data = MemoryDb::new().serialize();
+        utils::write_frame(&mut data, b"name");
+        MemoryDb::deserialize(&data);
+    }
+}
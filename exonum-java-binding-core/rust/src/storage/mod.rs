@@ -12,25 +12,50 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod batch;
 mod core_schema;
 mod db;
 mod entry;
+mod frozen_fork;
+mod hashed_proof_map_index;
 mod key_set_index;
 mod list_index;
+mod map_diff;
 mod map_index;
 mod memorydb;
 mod proof_list_index;
 mod proof_map_index;
+mod temporarydb;
 mod value_set_index;
+mod versioned_map_index;
 
+pub use self::batch::*;
 pub use self::core_schema::*;
-pub use self::db::Java_com_exonum_binding_storage_database_Views_nativeFree;
 pub(crate) use self::db::View;
+pub use self::db::{
+    Java_com_exonum_binding_storage_database_Fork_nativeCreateSavepoint,
+    Java_com_exonum_binding_storage_database_Fork_nativeFreezeFork,
+    Java_com_exonum_binding_storage_database_Fork_nativeIsDirty,
+    Java_com_exonum_binding_storage_database_Fork_nativeMemoryUsage,
+    Java_com_exonum_binding_storage_database_Fork_nativeReleaseSavepoint,
+    Java_com_exonum_binding_storage_database_Fork_nativeReplayOps,
+    Java_com_exonum_binding_storage_database_Fork_nativeRollbackTo,
+    Java_com_exonum_binding_storage_database_Views_nativeCanModify,
+    Java_com_exonum_binding_storage_database_Views_nativeFree,
+    Java_com_exonum_binding_storage_database_Views_nativeIndexExists,
+    Java_com_exonum_binding_storage_database_Views_nativeSnapshotId,
+    Java_com_exonum_binding_storage_database_Views_nativeStateHash,
+};
 pub use self::entry::*;
+pub use self::hashed_proof_map_index::*;
 pub use self::key_set_index::*;
 pub use self::list_index::*;
+pub use self::map_diff::*;
+pub(crate) use self::map_index::describe_map_index;
 pub use self::map_index::*;
 pub use self::memorydb::*;
 pub use self::proof_list_index::*;
 pub use self::proof_map_index::*;
+pub use self::temporarydb::*;
 pub use self::value_set_index::*;
+pub use self::versioned_map_index::*;
@@ -17,17 +17,28 @@ use exonum::storage::proof_list_index::{ListProof, ProofListIndexIter};
 use exonum::storage::{Fork, ProofListIndex, Snapshot};
 use jni::errors::Result;
 use jni::objects::{JClass, JObject, JString};
-use jni::sys::{jboolean, jbyteArray, jint, jlong, jobject};
+use jni::sys::{jboolean, jbyteArray, jint, jlong, jlongArray, jobject, jobjectArray};
 use jni::JNIEnv;
 
 use std::panic;
 use std::ptr;
+use std::result;
 
 use storage::db::{Value, View, ViewRef};
-use utils::{self, Handle};
+use utils::{self, Handle, ILLEGAL_ARGUMENT_EXCEPTION};
 
 type Index<T> = ProofListIndex<T, Value>;
 
+// The node tags of the serialized multi-range proofs, see `nativeGetMultiRangeProof`.
+const PROOF_FULL: u8 = 0;
+const PROOF_LEFT: u8 = 1;
+const PROOF_RIGHT: u8 = 2;
+const PROOF_LEAF: u8 = 3;
+
+// The tags of the serialized tail proofs, see `nativeGetTailProof`.
+const TAIL_PROOF_EMPTY: u8 = 0;
+const TAIL_PROOF_RANGE: u8 = 1;
+
 enum IndexType {
     SnapshotIndex(Index<&'static Snapshot>),
     ForkIndex(Index<&'static mut Fork>),
@@ -56,6 +67,10 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
 }
 
 /// Returns a pointer to the created `ProofListIndex` instance in an index family (= group).
+///
+/// The id is composed with `utils::compose_family_id` as a single part, so the index is the same
+/// as the one created with `nativeCreateInGroupMulti(group_name, [list_id])`, and different ids
+/// never collide with each other or with the multi-part ids.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexProxy_nativeCreateInGroup(
     env: JNIEnv,
@@ -67,6 +82,33 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
     let res = panic::catch_unwind(|| {
         let group_name = utils::convert_to_string(&env, group_name)?;
         let list_id = env.convert_byte_array(list_id)?;
+        let list_id = utils::compose_family_id(&[list_id.as_slice()]);
+        let view_ref = utils::cast_handle::<View>(view_handle).get();
+        Ok(utils::to_handle(match *view_ref {
+            ViewRef::Snapshot(snapshot) => {
+                IndexType::SnapshotIndex(Index::new_in_family(group_name, &list_id, &*snapshot))
+            }
+            ViewRef::Fork(ref mut fork) => {
+                IndexType::ForkIndex(Index::new_in_family(group_name, &list_id, fork))
+            }
+        }))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the created `ProofListIndex` instance in an index family (= group)
+/// with the id composed of several parts with `utils::compose_family_id`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexProxy_nativeCreateInGroupMulti(
+    env: JNIEnv,
+    _: JClass,
+    group_name: JString,
+    id_parts: jobjectArray,
+    view_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let group_name = utils::convert_to_string(&env, group_name)?;
+        let list_id = utils::convert_to_family_id(&env, id_parts)?;
         let view_ref = utils::cast_handle::<View>(view_handle).get();
         Ok(utils::to_handle(match *view_ref {
             ViewRef::Snapshot(snapshot) => {
@@ -90,7 +132,7 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
     utils::drop_handle::<IndexType>(&env, list_handle);
 }
 
-/// Returns the value by index. Null pointer is returned if value is not found.
+/// Returns the value by index. Throws `IndexOutOfBoundsException` if the index is out of bounds.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexProxy_nativeGet(
     env: JNIEnv,
@@ -99,13 +141,14 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
     index: jlong,
 ) -> jbyteArray {
     let res = panic::catch_unwind(|| {
-        let val = match *utils::cast_handle::<IndexType>(list_handle) {
-            IndexType::SnapshotIndex(ref list) => list.get(index as u64),
-            IndexType::ForkIndex(ref list) => list.get(index as u64),
+        let index = utils::convert_to_index(&env, index)?;
+        let (val, len) = match *utils::cast_handle::<IndexType>(list_handle) {
+            IndexType::SnapshotIndex(ref list) => (list.get(index), list.len()),
+            IndexType::ForkIndex(ref list) => (list.get(index), list.len()),
         };
         match val {
             Some(val) => env.byte_array_from_slice(&val),
-            None => Ok(ptr::null_mut()),
+            None => utils::check_index_in_bounds(&env, index, len).map(|_| ptr::null_mut()),
         }
     });
     utils::unwrap_exc_or(&env, res, ptr::null_mut())
@@ -199,6 +242,7 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
 }
 
 /// Returns Java representation of the proof that an element exists at the specified index.
+/// Throws `IndexOutOfBoundsException` if the index is negative.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexProxy_nativeGetProof(
     env: JNIEnv,
@@ -207,9 +251,10 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
     index: jlong,
 ) -> jobject {
     let res = panic::catch_unwind(|| {
+        let index = utils::convert_to_index(&env, index)?;
         let proof = match *utils::cast_handle::<IndexType>(list_handle) {
-            IndexType::SnapshotIndex(ref list) => list.get_proof(index as u64),
-            IndexType::ForkIndex(ref list) => list.get_proof(index as u64),
+            IndexType::SnapshotIndex(ref list) => list.get_proof(index),
+            IndexType::ForkIndex(ref list) => list.get_proof(index),
         };
         make_java_proof(&env, &proof).map(|x| x.into_inner())
     });
@@ -217,6 +262,7 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
 }
 
 /// Returns Java representation of the proof that some elements exists in the specified range.
+/// Throws `IndexOutOfBoundsException` if either bound is negative.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexProxy_nativeGetRangeProof(
     env: JNIEnv,
@@ -226,15 +272,92 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
     to: jlong,
 ) -> jobject {
     let res = panic::catch_unwind(|| {
+        let from = utils::convert_to_index(&env, from)?;
+        let to = utils::convert_to_index(&env, to)?;
         let proof = match *utils::cast_handle::<IndexType>(list_handle) {
-            IndexType::SnapshotIndex(ref list) => list.get_range_proof(from as u64, to as u64),
-            IndexType::ForkIndex(ref list) => list.get_range_proof(from as u64, to as u64),
+            IndexType::SnapshotIndex(ref list) => list.get_range_proof(from, to),
+            IndexType::ForkIndex(ref list) => list.get_range_proof(from, to),
         };
         make_java_proof(&env, &proof).map(|x| x.into_inner())
     });
     utils::unwrap_exc_or(&env, res, ptr::null_mut())
 }
 
+/// Returns the serialized proof that the elements exist in each of the specified ranges.
+///
+/// The ranges are given as the pairs of `from` (inclusive) and `to` (exclusive) indices,
+/// and must be sorted and non-overlapping. The combined proof is the tree of the range
+/// proofs merged together, serialized in the pre-order. Each node is its tag byte followed by:
+/// - `0` (full branch): the left and the right child;
+/// - `1` (left branch): the left child, and `1` and the 32-byte hash of the right child,
+///   or `0` if there is no right child;
+/// - `2` (right branch): the 32-byte hash of the left child and the right child;
+/// - `3` (leaf): the element as a frame, i.e., prefixed with its length as a big-endian `u32`.
+///
+/// Throws `IndexOutOfBoundsException` if a bound is negative, and `IllegalArgumentException`
+/// if the ranges are empty, unsorted or overlap.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexProxy_nativeGetMultiRangeProof(
+    env: JNIEnv,
+    _: JObject,
+    list_handle: Handle,
+    ranges: jlongArray,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let len = env.get_array_length(ranges)?;
+        let mut bounds = vec![0; len as usize];
+        env.get_long_array_region(ranges, 0, &mut bounds)?;
+        let mut indices = Vec::with_capacity(bounds.len());
+        for bound in bounds {
+            indices.push(utils::convert_to_index(&env, bound)?);
+        }
+        let ranges = to_ranges(&indices)
+            .or_else(|message| utils::throw_and_fail(&env, ILLEGAL_ARGUMENT_EXCEPTION, message))?;
+        let proof = match *utils::cast_handle::<IndexType>(list_handle) {
+            IndexType::SnapshotIndex(ref list) => multi_range_proof(list, &ranges),
+            IndexType::ForkIndex(ref list) => multi_range_proof(list, &ranges),
+        };
+        let mut buffer = Vec::new();
+        write_proof(&mut buffer, &proof);
+        env.byte_array_from_slice(&buffer)
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the serialized proof of all the elements starting at the index `from`
+/// up to the end of the list, which includes the values of the elements.
+///
+/// The proof is a tag byte followed by:
+/// - `0` (empty): nothing, if `from` is not less than the size of the list;
+/// - `1` (range): the range proof in the format of `nativeGetMultiRangeProof`.
+///
+/// Throws `IndexOutOfBoundsException` if `from` is negative.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexProxy_nativeGetTailProof(
+    env: JNIEnv,
+    _: JObject,
+    list_handle: Handle,
+    from: jlong,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let from = utils::convert_to_index(&env, from)?;
+        let proof = match *utils::cast_handle::<IndexType>(list_handle) {
+            IndexType::SnapshotIndex(ref list) => tail_proof(list, from),
+            IndexType::ForkIndex(ref list) => tail_proof(list, from),
+        };
+        let mut buffer = Vec::new();
+        match proof {
+            Some(proof) => {
+                buffer.push(TAIL_PROOF_RANGE);
+                write_proof(&mut buffer, &proof);
+            }
+            None => buffer.push(TAIL_PROOF_EMPTY),
+        }
+        env.byte_array_from_slice(&buffer)
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
 /// Returns pointer to the iterator over list.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexProxy_nativeCreateIter(
@@ -254,6 +377,7 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
 }
 
 /// Returns pointer to the iterator over list starting at given index.
+/// Throws `IndexOutOfBoundsException` if the index is negative.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexProxy_nativeIterFrom(
     env: JNIEnv,
@@ -262,10 +386,11 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
     index_from: jlong,
 ) -> Handle {
     let res = panic::catch_unwind(|| {
+        let index_from = utils::convert_to_index(&env, index_from)?;
         Ok(utils::to_handle(
             match *utils::cast_handle::<IndexType>(list_handle) {
-                IndexType::SnapshotIndex(ref list) => list.iter_from(index_from as u64),
-                IndexType::ForkIndex(ref list) => list.iter_from(index_from as u64),
+                IndexType::SnapshotIndex(ref list) => list.iter_from(index_from),
+                IndexType::ForkIndex(ref list) => list.iter_from(index_from),
             },
         ))
     });
@@ -293,7 +418,8 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
     utils::unwrap_exc_or_default(&env, res)
 }
 
-/// Sets value into specified index. Panics if `i` is out of bounds.
+/// Sets value into specified index. Throws `IndexOutOfBoundsException` if the index
+/// is out of bounds.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexProxy_nativeSet(
     env: JNIEnv,
@@ -307,8 +433,10 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
             panic!("Unable to modify snapshot.");
         }
         IndexType::ForkIndex(ref mut list) => {
+            let index = utils::convert_to_index(&env, index)?;
+            utils::check_index_in_bounds(&env, index, list.len())?;
             let value = env.convert_byte_array(value)?;
-            list.set(index as u64, value);
+            list.set(index, value);
             Ok(())
         }
     });
@@ -361,6 +489,106 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofListIndexPro
     utils::drop_handle::<ProofListIndexIter<Value>>(&env, iter_handle);
 }
 
+/// Converts the flat array of range bounds into ranges, checking that they are non-empty,
+/// sorted and non-overlapping. Returns an error message otherwise.
+fn to_ranges(bounds: &[u64]) -> result::Result<Vec<(u64, u64)>, String> {
+    if bounds.is_empty() || bounds.len() % 2 != 0 {
+        return Err("The ranges must be non-empty pairs of bounds".to_owned());
+    }
+    let ranges: Vec<_> = bounds.chunks(2).map(|range| (range[0], range[1])).collect();
+    for &(from, to) in &ranges {
+        if from >= to {
+            return Err(format!("Invalid range: [{}, {})", from, to));
+        }
+    }
+    for pair in ranges.windows(2) {
+        if pair[0].1 > pair[1].0 {
+            return Err(format!(
+                "The ranges are not sorted or overlap: [{}, {}) and [{}, {})",
+                pair[0].0, pair[0].1, pair[1].0, pair[1].1
+            ));
+        }
+    }
+    Ok(ranges)
+}
+
+/// Returns the proof of all the given ranges, made by merging their range proofs.
+fn multi_range_proof<T: AsRef<Snapshot>>(
+    list: &Index<T>,
+    ranges: &[(u64, u64)],
+) -> ListProof<Value> {
+    ranges
+        .iter()
+        .map(|&(from, to)| list.get_range_proof(from, to))
+        .fold(None, |merged, proof| match merged {
+            Some(merged) => Some(merge_proofs(merged, proof)),
+            None => Some(proof),
+        }).expect("No ranges")
+}
+
+/// Returns the proof of the elements from `from` to the end of the list, or `None`
+/// if there are no such elements.
+fn tail_proof<T: AsRef<Snapshot>>(list: &Index<T>, from: u64) -> Option<ListProof<Value>> {
+    let len = list.len();
+    if from >= len {
+        return None;
+    }
+    Some(list.get_range_proof(from, len))
+}
+
+/// Merges two proofs of the same list into the proof of the elements of both of them.
+///
+/// The proofs share the structure of the list tree, so a subtree present in one proof
+/// replaces its hash in the other one.
+fn merge_proofs(first: ListProof<Value>, second: ListProof<Value>) -> ListProof<Value> {
+    use self::ListProof::*;
+
+    let merge = |first: Box<ListProof<Value>>, second: Box<ListProof<Value>>| {
+        Box::new(merge_proofs(*first, *second))
+    };
+    match (first, second) {
+        (Full(l1, r1), Full(l2, r2)) => Full(merge(l1, l2), merge(r1, r2)),
+        (Full(l1, r), Left(l2, _)) | (Left(l1, _), Full(l2, r)) => Full(merge(l1, l2), r),
+        (Full(l, r1), Right(_, r2)) | (Right(_, r1), Full(l, r2)) => Full(l, merge(r1, r2)),
+        (Left(l1, hash), Left(l2, _)) => Left(merge(l1, l2), hash),
+        (Left(l, _), Right(_, r)) | (Right(_, r), Left(l, _)) => Full(l, r),
+        (Right(hash, r1), Right(_, r2)) => Right(hash, merge(r1, r2)),
+        (Leaf(value), Leaf(_)) => Leaf(value),
+        _ => panic!("The proofs do not belong to the same list"),
+    }
+}
+
+/// Serializes the proof in the format described in `nativeGetMultiRangeProof`.
+fn write_proof(buffer: &mut Vec<u8>, proof: &ListProof<Value>) {
+    match *proof {
+        ListProof::Full(ref left, ref right) => {
+            buffer.push(PROOF_FULL);
+            write_proof(buffer, left);
+            write_proof(buffer, right);
+        }
+        ListProof::Left(ref left, ref hash) => {
+            buffer.push(PROOF_LEFT);
+            write_proof(buffer, left);
+            match *hash {
+                Some(ref hash) => {
+                    buffer.push(1);
+                    buffer.extend_from_slice(hash.as_ref());
+                }
+                None => buffer.push(0),
+            }
+        }
+        ListProof::Right(ref hash, ref right) => {
+            buffer.push(PROOF_RIGHT);
+            buffer.extend_from_slice(hash.as_ref());
+            write_proof(buffer, right);
+        }
+        ListProof::Leaf(ref value) => {
+            buffer.push(PROOF_LEAF);
+            utils::write_frame(buffer, value);
+        }
+    }
+}
+
 fn make_java_proof<'a>(env: &JNIEnv<'a>, proof: &ListProof<Value>) -> Result<JObject<'a>> {
     match *proof {
         ListProof::Full(ref left, ref right) => {
@@ -418,3 +646,67 @@ fn make_java_hash_node<'a>(env: &JNIEnv<'a>, hash: &Hash) -> Result<JObject<'a>>
         &[hash.as_obj().into()],
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use exonum::storage::{Database, MemoryDB};
+
+    use super::*;
+
+    #[test]
+    fn merge_range_proofs() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        let mut list: Index<_> = ProofListIndex::new("list", &mut fork);
+        for i in 0..7_u8 {
+            list.push(vec![i]);
+        }
+
+        let proof = multi_range_proof(&list, &to_ranges(&[0, 2, 3, 4, 6, 7]).unwrap());
+        let elements: Vec<_> = proof
+            .validate(list.merkle_root(), list.len())
+            .unwrap()
+            .into_iter()
+            .map(|(index, value)| (index, value.clone()))
+            .collect();
+        assert_eq!(
+            vec![(0, vec![0]), (1, vec![1]), (3, vec![3]), (6, vec![6])],
+            elements
+        );
+    }
+
+    #[test]
+    fn tail_proofs() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        let mut list: Index<_> = ProofListIndex::new("list", &mut fork);
+        assert!(tail_proof(&list, 0).is_none());
+        for i in 0..5_u8 {
+            list.push(vec![i]);
+        }
+
+        let proof = tail_proof(&list, 2).unwrap();
+        let elements: Vec<_> = proof
+            .validate(list.merkle_root(), list.len())
+            .unwrap()
+            .into_iter()
+            .map(|(index, value)| (index, value.clone()))
+            .collect();
+        assert_eq!(vec![(2, vec![2]), (3, vec![3]), (4, vec![4])], elements);
+        assert!(tail_proof(&list, 5).is_none());
+        assert!(tail_proof(&list, u64::max_value()).is_none());
+    }
+
+    #[test]
+    fn overlapping_ranges() {
+        assert_eq!(
+            Err("The ranges are not sorted or overlap: [0, 3) and [2, 4)".to_owned()),
+            to_ranges(&[0, 3, 2, 4])
+        );
+    }
+
+    #[test]
+    fn empty_range() {
+        assert_eq!(Err("Invalid range: [1, 1)".to_owned()), to_ranges(&[1, 1]));
+    }
+}
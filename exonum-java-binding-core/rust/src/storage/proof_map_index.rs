@@ -13,18 +13,20 @@
 // limitations under the License.
 
 use jni::objects::{JClass, JObject, JString};
-use jni::sys::{jboolean, jbyteArray, jobject, jsize};
+use jni::sys::{jboolean, jbyteArray, jobject, jobjectArray, jsize, JNI_TRUE};
 use jni::JNIEnv;
 
 use std::panic;
 use std::ptr;
+use std::vec;
 
 use exonum::crypto::Hash;
 use exonum::storage::proof_map_index::{
     CheckedMapProof, MapProof, ProofMapIndexIter, ProofMapIndexKeys, ProofMapIndexValues,
     ProofPath, PROOF_MAP_KEY_SIZE,
 };
-use exonum::storage::{Fork, ProofMapIndex, Snapshot};
+use exonum::storage::{Database, Fork, MemoryDB, ProofMapIndex, Snapshot};
+use serde_json;
 
 use storage::db::{Value, View, ViewRef};
 use utils::{self, Handle, PairIter};
@@ -47,6 +49,7 @@ enum IndexType {
 }
 
 type Iter<'a> = PairIter<ProofMapIndexIter<'a, Key, Value>>;
+type KeysInProofOrder = vec::IntoIter<Key>;
 
 /// Returns a pointer to the created `ProofMapIndex` object.
 #[no_mangle]
@@ -70,7 +73,31 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProx
     utils::unwrap_exc_or_default(&env, res)
 }
 
+/// Returns a pointer to the created `ProofMapIndex` object with fixed-length keys.
+///
+/// The keys must be exactly `PROOF_MAP_KEY_SIZE` bytes long and are stored as is, so
+/// the root hash and the proofs are over the raw keys. Same as `nativeCreate`; see
+/// `HashedProofMapIndexProxy` for the maps with arbitrary keys.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeCreateRaw(
+    env: JNIEnv,
+    class: JClass,
+    name: JString,
+    view_handle: Handle,
+) -> Handle {
+    Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeCreate(
+        env,
+        class,
+        name,
+        view_handle,
+    )
+}
+
 /// Returns a pointer to the created `ProofMapIndex` instance in an index family (= group).
+///
+/// The id is composed with `utils::compose_family_id` as a single part, so the index is the same
+/// as the one created with `nativeCreateInGroupMulti(group_name, [map_id])`, and different ids
+/// never collide with each other or with the multi-part ids.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeCreateInGroup(
     env: JNIEnv,
@@ -82,6 +109,33 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProx
     let res = panic::catch_unwind(|| {
         let group_name = utils::convert_to_string(&env, group_name)?;
         let map_id = env.convert_byte_array(map_id)?;
+        let map_id = utils::compose_family_id(&[map_id.as_slice()]);
+        let view_ref = utils::cast_handle::<View>(view_handle).get();
+        Ok(utils::to_handle(match *view_ref {
+            ViewRef::Snapshot(snapshot) => {
+                IndexType::SnapshotIndex(Index::new_in_family(group_name, &map_id, &*snapshot))
+            }
+            ViewRef::Fork(ref mut fork) => {
+                IndexType::ForkIndex(Index::new_in_family(group_name, &map_id, fork))
+            }
+        }))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the created `ProofMapIndex` instance in an index family (= group)
+/// with the id composed of several parts with `utils::compose_family_id`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeCreateInGroupMulti(
+    env: JNIEnv,
+    _: JClass,
+    group_name: JString,
+    id_parts: jobjectArray,
+    view_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let group_name = utils::convert_to_string(&env, group_name)?;
+        let map_id = utils::convert_to_family_id(&env, id_parts)?;
         let view_ref = utils::cast_handle::<View>(view_handle).get();
         Ok(utils::to_handle(match *view_ref {
             ViewRef::Snapshot(snapshot) => {
@@ -122,6 +176,28 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProx
     utils::unwrap_exc_or(&env, res, ptr::null_mut())
 }
 
+/// Returns `true` if the root hash of the proof map matches the root hash of a proof map
+/// built from scratch with the same entries.
+///
+/// The entries are copied into a map in a temporary `MemoryDB`, so the check takes time
+/// and memory proportional to the size of the map, and is intended for debugging
+/// and integrity checks in test environments.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeVerifyConsistency(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> jboolean {
+    let res = panic::catch_unwind(|| {
+        let consistent = match *utils::cast_handle::<IndexType>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.merkle_root() == recompute_root(map),
+            IndexType::ForkIndex(ref map) => map.merkle_root() == recompute_root(map),
+        };
+        Ok(consistent as jboolean)
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
 /// Returns value identified by the `key`. Null pointer is returned if value is not found.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeGet(
@@ -145,6 +221,8 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProx
 }
 
 /// Returns `true` if the map contains a value for the specified key.
+///
+/// Uses `ProofMapIndex::contains`, which neither builds a proof nor copies the value.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeContainsKey(
     env: JNIEnv,
@@ -202,7 +280,104 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProx
     utils::unwrap_exc_or(&env, res, ptr::null_mut())
 }
 
-fn convert_to_java_proof<'a>(
+/// Returns a two-element array with the value identified by the `key`, which is null if
+/// the value is not found, and the proof for the `key` if `include_proof` is `true`,
+/// or null otherwise. The proof is only built if it is requested.
+///
+/// The proof is serialized in the JSON format of `MapProof` accepted by `nativeVerifyMapProof`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeGetWithProof(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+    include_proof: jboolean,
+) -> jobjectArray {
+    let res = panic::catch_unwind(|| {
+        let key = convert_to_key(&env, key)?;
+        let (value, proof) = match *utils::cast_handle::<IndexType>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => get_with_proof(map, key, include_proof),
+            IndexType::ForkIndex(ref map) => get_with_proof(map, key, include_proof),
+        };
+        let result = env.new_object_array(2, BYTE_ARRAY, JObject::null())?;
+        if let Some(value) = value {
+            let value: JObject = env.byte_array_from_slice(&value)?.into();
+            env.set_object_array_element(result, 0, value)?;
+        }
+        if let Some(proof) = proof {
+            let proof = serde_json::to_vec(&proof).expect("Unable to serialize map proof");
+            let proof: JObject = env.byte_array_from_slice(&proof)?.into();
+            env.set_object_array_element(result, 1, proof)?;
+        }
+        Ok(result)
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Verifies the map proof for the `key` and returns the value proven to be stored
+/// for the `key`. Null pointer is returned if the proof proves the absence of the `key`.
+///
+/// The proof is expected in the JSON format of `MapProof` used by the REST API of a node.
+/// Panics if the proof cannot be deserialized, is invalid, does not match
+/// the `expected_root` or does not contain the `key`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeVerifyMapProof(
+    env: JNIEnv,
+    _: JClass,
+    proof_bytes: jbyteArray,
+    expected_root: jbyteArray,
+    key: jbyteArray,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let proof_bytes = env.convert_byte_array(proof_bytes)?;
+        let expected_root = utils::convert_to_hash(&env, expected_root)?;
+        let key = convert_to_key(&env, key)?;
+        let proof: MapProof<Key, Value> =
+            serde_json::from_slice(&proof_bytes).expect("Unable to deserialize map proof");
+        let checked_proof = proof.check().expect("Invalid map proof");
+        assert_eq!(
+            expected_root,
+            checked_proof.merkle_root(),
+            "Map proof root hash mismatch"
+        );
+        if let Some(&(_, value)) = checked_proof.entries().iter().find(|e| *e.0 == key) {
+            return env.byte_array_from_slice(value);
+        }
+        assert!(
+            checked_proof.missing_keys().into_iter().any(|k| *k == key),
+            "Map proof does not contain the key"
+        );
+        Ok(ptr::null_mut())
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Returns the root hash of a proof map with the entries of the given map, which is built
+/// in a temporary database.
+fn recompute_root<T: AsRef<Snapshot>>(map: &Index<T>) -> Hash {
+    let mut fork = MemoryDB::new().fork();
+    let mut copy: Index<&mut Fork> = Index::new("copy", &mut fork);
+    for (key, value) in map.iter() {
+        copy.put(&key, value);
+    }
+    copy.merkle_root()
+}
+
+fn get_with_proof<T: AsRef<Snapshot>>(
+    map: &Index<T>,
+    key: Key,
+    include_proof: jboolean,
+) -> (Option<Value>, Option<MapProof<Key, Value>>) {
+    let value = map.get(&key);
+    let proof = if include_proof == JNI_TRUE {
+        Some(map.get_proof(key))
+    } else {
+        None
+    };
+    (value, proof)
+}
+
+pub(crate) fn convert_to_java_proof<'a>(
     env: &'a JNIEnv,
     proof: MapProof<Key, Value>,
 ) -> JniResult<JObject<'a>> {
@@ -350,6 +525,35 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProx
     utils::unwrap_exc_or_default(&env, res)
 }
 
+/// Returns the pointer to the iterator over map keys in the order the Merkle Patricia tree
+/// of the map visits its leaves, which shall be consumed with `nativeKeysInProofOrderNext`.
+///
+/// The tree branches on the bits of the keys starting from the least significant bit
+/// of the first byte, going to the left child on `0`. So the keys are ordered by their
+/// first byte with the bits reversed, then by the second one, and so on. For example,
+/// a key starting with `0x02` precedes a key starting with `0x01`. The plain keys iterator,
+/// as the ones of `MapIndex`, orders the keys by their bytes, so keys `0x01...` precede
+/// `0x02...` there.
+///
+/// The keys are collected and sorted when the iterator is created.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeCreateKeysIterProofOrder(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let mut keys: Vec<Key> = match *utils::cast_handle::<IndexType>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.keys().collect(),
+            IndexType::ForkIndex(ref map) => map.keys().collect(),
+        };
+        sort_in_proof_order(&mut keys);
+        let iter: KeysInProofOrder = keys.into_iter();
+        Ok(utils::to_handle(iter))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
 /// Returns the pointer to the iterator over map values.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeCreateValuesIter(
@@ -451,6 +655,42 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProx
     utils::unwrap_exc_or_default(&env, res)
 }
 
+/// Puts `values[i]` identified by the `i`-th key for each `i` into the index and returns
+/// the root hash of the map afterwards.
+///
+/// `keys` are passed as a single array, the concatenation of the 32-byte keys.
+/// All the pairs are put within a single call and the root hash is obtained only once,
+/// at the end, so the intermediate root hashes are not observable. Note that the tree nodes
+/// on the path of each key are still updated by each put.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeBulkPut(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    keys: jbyteArray,
+    values: jobjectArray,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| match *utils::cast_handle::<IndexType>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map) => {
+            let keys = convert_to_keys(&env, keys)?;
+            let values = utils::convert_to_byte_arrays(&env, values)?;
+            assert_eq!(
+                keys.len(),
+                values.len(),
+                "Keys and values must have the same length"
+            );
+            for (key, value) in keys.iter().zip(values) {
+                map.put(key, value);
+            }
+            utils::convert_hash(&env, &map.merkle_root())
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
 /// Removes value identified by the `key` from the index.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeRemove(
@@ -554,6 +794,34 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProx
     utils::drop_handle::<ProofMapIndexKeys<Key>>(&env, iter_handle);
 }
 
+/// Returns the next key from the iterator created by `nativeCreateKeysIterProofOrder`.
+/// Returns null pointer when iteration is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeKeysInProofOrderNext(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let iter = utils::cast_handle::<KeysInProofOrder>(iter_handle);
+        match iter.next() {
+            Some(val) => env.byte_array_from_slice(&val),
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Destroys the iterator created by `nativeCreateKeysIterProofOrder` and frees memory.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeKeysInProofOrderFree(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) {
+    utils::drop_handle::<KeysInProofOrder>(&env, iter_handle);
+}
+
 /// Return next value from the values-iterator. Returns null pointer when iteration is finished.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ProofMapIndexProxy_nativeValuesIterNext(
@@ -603,3 +871,49 @@ fn convert_to_keys(env: &JNIEnv, array: jbyteArray) -> JniResult<Vec<Key>> {
         }).collect();
     Ok(keys)
 }
+
+/// Sorts the keys in the order of the leaves of the Merkle Patricia tree, i.e., by the bits
+/// of the keys from the least significant bit of each byte to the most significant one.
+fn sort_in_proof_order(keys: &mut [Key]) {
+    keys.sort_by_key(|key| {
+        let mut reversed = *key;
+        for byte in reversed.iter_mut() {
+            *byte = reverse_bits(*byte);
+        }
+        reversed
+    });
+}
+
+fn reverse_bits(byte: u8) -> u8 {
+    (0..8).fold(0, |reversed, bit| {
+        reversed | (((byte >> bit) & 1) << (7 - bit))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(first_byte: u8, second_byte: u8) -> Key {
+        let mut key = Key::default();
+        key[0] = first_byte;
+        key[1] = second_byte;
+        key
+    }
+
+    #[test]
+    fn reverse_bits_of_byte() {
+        assert_eq!(0b1000_0000, reverse_bits(0b0000_0001));
+        assert_eq!(0b0011_0101, reverse_bits(0b1010_1100));
+    }
+
+    #[test]
+    fn keys_in_proof_order() {
+        let mut keys = vec![key(1, 0), key(2, 0), key(0, 1), key(0, 0), key(3, 0)];
+        sort_in_proof_order(&mut keys);
+        assert_eq!(
+            vec![key(0, 0), key(0, 1), key(2, 0), key(1, 0), key(3, 0)],
+            keys
+        );
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use exonum::storage::{Database, DbOptions, RocksDB};
+use jni::objects::{JClass, JObject};
+use jni::JNIEnv;
+use tempdir::TempDir;
+
+use std::panic;
+
+use storage::db::{View, ViewRef};
+use utils::{self, Handle};
+
+const TEMP_DIR_PREFIX: &str = "ejb-temporary-db";
+
+/// A `RocksDB` database in a temporary directory, which is removed when the database is dropped.
+///
+/// Unlike `MemoryDB`, it exercises the same code paths as the on-disk storage of a node,
+/// so it is intended for tests.
+pub(crate) struct TemporaryDb {
+    // The fields are dropped in the declaration order, so the database is closed
+    // before its directory is removed.
+    db: RocksDB,
+    _dir: TempDir,
+}
+
+impl TemporaryDb {
+    fn new() -> Self {
+        let dir = TempDir::new(TEMP_DIR_PREFIX).expect("Unable to create a temporary directory");
+        let db = RocksDB::open(dir.path(), &DbOptions::default()).expect("Unable to open RocksDB");
+        TemporaryDb { db, _dir: dir }
+    }
+
+    /// Returns the underlying database.
+    pub fn database(&self) -> &RocksDB {
+        &self.db
+    }
+}
+
+/// Returns pointer to created `TemporaryDb` object.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_TemporaryDb_nativeCreate(
+    env: JNIEnv,
+    _: JClass,
+) -> Handle {
+    let res = panic::catch_unwind(|| Ok(utils::to_handle(TemporaryDb::new())));
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Destroys underlying `TemporaryDb` object, removes its directory and frees memory.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_TemporaryDb_nativeFree(
+    env: JNIEnv,
+    _: JClass,
+    db_handle: Handle,
+) {
+    utils::drop_handle::<TemporaryDb>(&env, db_handle);
+}
+
+/// Returns pointer to created `Snapshot` object.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_TemporaryDb_nativeCreateSnapshot(
+    env: JNIEnv,
+    _: JObject,
+    db_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let snapshot = utils::cast_handle::<TemporaryDb>(db_handle).db.snapshot();
+        Ok(utils::to_handle(View::from_owned_snapshot(snapshot)))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns pointer to created `Fork` object.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_TemporaryDb_nativeCreateFork(
+    env: JNIEnv,
+    _: JObject,
+    db_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let db = &utils::cast_handle::<TemporaryDb>(db_handle).db;
+        let snapshot = db.snapshot();
+        let mut view = View::from_owned_fork(db.fork());
+        view.set_base_snapshot(snapshot);
+        Ok(utils::to_handle(view))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Merges the given fork into the database.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_TemporaryDb_nativeMerge(
+    env: JNIEnv,
+    _: JObject,
+    db_handle: Handle,
+    view_handle: Handle,
+) {
+    let res = panic::catch_unwind(|| {
+        let db = utils::cast_handle::<TemporaryDb>(db_handle);
+        let fork = match *utils::cast_handle::<View>(view_handle).get() {
+            ViewRef::Snapshot(_) => panic!("Attempt to merge snapshot instead of fork."),
+            ViewRef::Fork(ref fork) => fork,
+        };
+        db.db
+            .merge(fork.patch().clone())
+            .expect("Unable to merge fork");
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Makes sure that all the merged data is persisted on disk.
+///
+/// As `RocksDB` does not expose a flush operation, an empty patch is merged with the `sync`
+/// write option, which makes RocksDB sync its write-ahead log. An I/O error is
+/// thrown as `java.lang.RuntimeException`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_database_TemporaryDb_nativeFlush(
+    env: JNIEnv,
+    _: JObject,
+    db_handle: Handle,
+) {
+    let res = panic::catch_unwind(|| {
+        let db = utils::cast_handle::<TemporaryDb>(db_handle);
+        let empty_patch = db.db.fork().into_patch();
+        db.db
+            .merge_sync(empty_patch)
+            .expect("Unable to flush database");
+        Ok(())
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum::storage::Entry;
+
+    use super::*;
+
+    #[test]
+    fn removes_directory_on_drop() {
+        let db = TemporaryDb::new();
+        let path = db._dir.path().to_owned();
+        let mut fork = db.db.fork();
+        Entry::new("test", &mut fork).set(1_u64);
+        db.db.merge(fork.into_patch()).unwrap();
+        assert!(path.exists());
+
+        drop(db);
+        assert!(!path.exists());
+    }
+}
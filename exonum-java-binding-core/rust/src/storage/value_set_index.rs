@@ -15,7 +15,7 @@
 use exonum::storage::value_set_index::{ValueSetIndexHashes, ValueSetIndexIter};
 use exonum::storage::{Fork, Snapshot, ValueSetIndex};
 use jni::objects::{JClass, JObject, JString};
-use jni::sys::{jboolean, jbyteArray, jobject};
+use jni::sys::{jboolean, jbyteArray, jobject, jobjectArray};
 use jni::JNIEnv;
 
 use std::panic;
@@ -58,6 +58,10 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ValueSetIndexProx
 }
 
 /// Returns a pointer to the created `ValueSetIndex` instance in an index family (= group).
+///
+/// The id is composed with `utils::compose_family_id` as a single part, so the index is the same
+/// as the one created with `nativeCreateInGroupMulti(group_name, [set_id])`, and different ids
+/// never collide with each other or with the multi-part ids.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ValueSetIndexProxy_nativeCreateInGroup(
     env: JNIEnv,
@@ -69,6 +73,33 @@ pub extern "system" fn Java_com_exonum_binding_storage_indices_ValueSetIndexProx
     let res = panic::catch_unwind(|| {
         let group_name = utils::convert_to_string(&env, group_name)?;
         let set_id = env.convert_byte_array(set_id)?;
+        let set_id = utils::compose_family_id(&[set_id.as_slice()]);
+        let view_ref = utils::cast_handle::<View>(view_handle).get();
+        Ok(utils::to_handle(match *view_ref {
+            ViewRef::Snapshot(snapshot) => {
+                IndexType::SnapshotIndex(Index::new_in_family(group_name, &set_id, &*snapshot))
+            }
+            ViewRef::Fork(ref mut fork) => {
+                IndexType::ForkIndex(Index::new_in_family(group_name, &set_id, fork))
+            }
+        }))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns a pointer to the created `ValueSetIndex` instance in an index family (= group)
+/// with the id composed of several parts with `utils::compose_family_id`.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ValueSetIndexProxy_nativeCreateInGroupMulti(
+    env: JNIEnv,
+    _: JClass,
+    group_name: JString,
+    id_parts: jobjectArray,
+    view_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let group_name = utils::convert_to_string(&env, group_name)?;
+        let set_id = utils::convert_to_family_id(&env, id_parts)?;
         let view_ref = utils::cast_handle::<View>(view_handle).get();
         Ok(utils::to_handle(match *view_ref {
             ViewRef::Snapshot(snapshot) => {
@@ -128,6 +159,33 @@ pub extern "C" fn Java_com_exonum_binding_storage_indices_ValueSetIndexProxy_nat
     utils::unwrap_exc_or_default(&env, res)
 }
 
+/// Returns the value with the specified hash. Null pointer is returned if the set
+/// does not contain such value.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_ValueSetIndexProxy_nativeGetByHash(
+    env: JNIEnv,
+    _: JObject,
+    set_handle: Handle,
+    hash: jbyteArray,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let hash = utils::convert_to_hash(&env, hash)?;
+        // The values are stored under their hashes, so the lookup is the first entry
+        // starting from the hash, if the hash matches.
+        let entry = match *utils::cast_handle::<IndexType>(set_handle) {
+            IndexType::SnapshotIndex(ref set) => set.iter_from(&hash).next(),
+            IndexType::ForkIndex(ref set) => set.iter_from(&hash).next(),
+        };
+        match entry {
+            Some((ref entry_hash, ref value)) if *entry_hash == hash => {
+                utils::convert_bytes(&env, value)
+            }
+            _ => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
 /// Returns the pointer to the iterator over a set that returns a pair of value and its hash.
 #[no_mangle]
 pub extern "system" fn Java_com_exonum_binding_storage_indices_ValueSetIndexProxy_nativeCreateIterator(
@@ -0,0 +1,330 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A map that allows to get the entries modified after a given sequence number.
+//!
+//! The entries are stored in a `MapIndex` with the name of the versioned map, so they can be
+//! read with `MapIndexProxy` as well. Three sibling indices track the modifications:
+//! - `<name>.sequence` — an `Entry` with the last assigned sequence number;
+//! - `<name>.versions` — a `MapIndex` from each key to the sequence number of its last `put`;
+//! - `<name>.changes` — a `MapIndex` from each sequence number to the key it was assigned to.
+//!
+//! Each `put` assigns the next sequence number (starting with 1) to the key, replacing the
+//! previous one in `<name>.changes`, so the entries modified after a sequence number are found
+//! by iterating `<name>.changes` from the next number. A `remove` deletes the key from all
+//! the indices without assigning a sequence number, so removals are not reported by
+//! `nativeEntriesSince`; a tombstone value can be put instead if they must be.
+//!
+//! All the modifications must be made through `VersionedMapIndexProxy` to keep
+//! the indices consistent.
+
+use exonum::storage::{Entry, Fork, MapIndex, Snapshot};
+use jni::objects::{JClass, JObject, JString};
+use jni::sys::{jbyteArray, jlong, jobject};
+use jni::JNIEnv;
+
+use std::panic;
+use std::ptr;
+use std::vec;
+
+use storage::db::{Key, Value, View, ViewRef};
+use utils::{self, Handle, PairIter};
+
+type Sequence = u64;
+
+struct VersionedMap<T> {
+    name: String,
+    view: T,
+}
+
+enum IndexType {
+    SnapshotIndex(VersionedMap<&'static Snapshot>),
+    ForkIndex(VersionedMap<&'static mut Fork>),
+}
+
+type Iter = PairIter<vec::IntoIter<(Key, Value)>>;
+
+const JAVA_ENTRY_FQN: &str = "com/exonum/binding/storage/indices/MapEntryInternal";
+
+impl<T> VersionedMap<T> {
+    fn sequence_name(&self) -> String {
+        format!("{}.sequence", self.name)
+    }
+
+    fn versions_name(&self) -> String {
+        format!("{}.versions", self.name)
+    }
+
+    fn changes_name(&self) -> String {
+        format!("{}.changes", self.name)
+    }
+}
+
+impl<T: AsRef<Snapshot>> VersionedMap<T> {
+    #[cfg_attr(feature = "cargo-clippy", allow(ptr_arg))]
+    fn get(&self, key: &Key) -> Option<Value> {
+        let snapshot: &Snapshot = self.view.as_ref();
+        MapIndex::new(self.name.clone(), snapshot).get(key)
+    }
+
+    /// Returns the entries with sequence numbers greater than `sequence`
+    /// in the order of modification.
+    fn entries_since(&self, sequence: Sequence) -> Vec<(Key, Value)> {
+        let start = match sequence.checked_add(1) {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+        let snapshot: &Snapshot = self.view.as_ref();
+        let values: MapIndex<_, Key, Value> = MapIndex::new(self.name.clone(), snapshot);
+        let changes: MapIndex<_, Sequence, Key> = MapIndex::new(self.changes_name(), snapshot);
+        changes
+            .iter_from(&start)
+            .map(|(_, key)| {
+                let value = values.get(&key).expect("Inconsistent versioned map");
+                (key, value)
+            }).collect()
+    }
+}
+
+impl<'a> VersionedMap<&'a mut Fork> {
+    #[cfg_attr(feature = "cargo-clippy", allow(ptr_arg))]
+    fn put(&mut self, key: &Key, value: Value) {
+        let sequence = {
+            let mut last: Entry<_, Sequence> = Entry::new(self.sequence_name(), &mut *self.view);
+            let sequence = last.get().unwrap_or(0) + 1;
+            last.set(sequence);
+            sequence
+        };
+        self.remove_version(key);
+        MapIndex::new(self.versions_name(), &mut *self.view).put(key, sequence);
+        MapIndex::new(self.changes_name(), &mut *self.view).put(&sequence, key.clone());
+        MapIndex::new(self.name.clone(), &mut *self.view).put(key, value);
+    }
+
+    #[cfg_attr(feature = "cargo-clippy", allow(ptr_arg))]
+    fn remove(&mut self, key: &Key) {
+        self.remove_version(key);
+        let mut values: MapIndex<_, Key, Value> = MapIndex::new(self.name.clone(), &mut *self.view);
+        values.remove(key);
+    }
+
+    #[cfg_attr(feature = "cargo-clippy", allow(ptr_arg))]
+    fn remove_version(&mut self, key: &Key) {
+        let mut versions: MapIndex<_, Key, Sequence> =
+            MapIndex::new(self.versions_name(), &mut *self.view);
+        if let Some(sequence) = versions.get(key) {
+            versions.remove(key);
+            let mut changes: MapIndex<_, Sequence, Key> =
+                MapIndex::new(self.changes_name(), &mut *self.view);
+            changes.remove(&sequence);
+        }
+    }
+}
+
+/// Returns a pointer to the created versioned map object.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_VersionedMapIndexProxy_nativeCreate(
+    env: JNIEnv,
+    _: JClass,
+    name: JString,
+    view_handle: Handle,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let name = utils::convert_to_string(&env, name)?;
+        Ok(utils::to_handle(
+            match *utils::cast_handle::<View>(view_handle).get() {
+                ViewRef::Snapshot(snapshot) => IndexType::SnapshotIndex(VersionedMap {
+                    name,
+                    view: snapshot,
+                }),
+                ViewRef::Fork(ref mut fork) => {
+                    IndexType::ForkIndex(VersionedMap { name, view: fork })
+                }
+            },
+        ))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Destroys the underlying versioned map object and frees memory.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_VersionedMapIndexProxy_nativeFree(
+    env: JNIEnv,
+    _: JClass,
+    map_handle: Handle,
+) {
+    utils::drop_handle::<IndexType>(&env, map_handle);
+}
+
+/// Returns value identified by the `key`. Null pointer is returned if value is not found.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_VersionedMapIndexProxy_nativeGet(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+) -> jbyteArray {
+    let res = panic::catch_unwind(|| {
+        let key = env.convert_byte_array(key)?;
+        let val = match *utils::cast_handle::<IndexType>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.get(&key),
+            IndexType::ForkIndex(ref map) => map.get(&key),
+        };
+        match val {
+            Some(val) => env.byte_array_from_slice(&val),
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Sets `value` identified by the `key` into the map, assigning the next sequence number
+/// to the key.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_VersionedMapIndexProxy_nativePut(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+    value: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| match *utils::cast_handle::<IndexType>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map) => {
+            let key = env.convert_byte_array(key)?;
+            let value = env.convert_byte_array(value)?;
+            map.put(&key, value);
+            Ok(())
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Removes value identified by the `key` from the map along with its sequence number.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_VersionedMapIndexProxy_nativeRemove(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    key: jbyteArray,
+) {
+    let res = panic::catch_unwind(|| match *utils::cast_handle::<IndexType>(map_handle) {
+        IndexType::SnapshotIndex(_) => {
+            panic!("Unable to modify snapshot.");
+        }
+        IndexType::ForkIndex(ref mut map) => {
+            let key = env.convert_byte_array(key)?;
+            map.remove(&key);
+            Ok(())
+        }
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the pointer to the iterator over the entries which were put after the given
+/// sequence number, in the order of modification. A negative `seq` is treated as zero,
+/// so all the entries are returned.
+///
+/// The entries are collected when the iterator is created, so the iterator is not affected
+/// by the subsequent modifications of the map.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_VersionedMapIndexProxy_nativeEntriesSince(
+    env: JNIEnv,
+    _: JObject,
+    map_handle: Handle,
+    seq: jlong,
+) -> Handle {
+    let res = panic::catch_unwind(|| {
+        let seq = if seq < 0 { 0 } else { seq as Sequence };
+        let entries = match *utils::cast_handle::<IndexType>(map_handle) {
+            IndexType::SnapshotIndex(ref map) => map.entries_since(seq),
+            IndexType::ForkIndex(ref map) => map.entries_since(seq),
+        };
+        let iter = Iter::new(&env, entries.into_iter(), JAVA_ENTRY_FQN)?;
+        Ok(utils::to_handle(iter))
+    });
+    utils::unwrap_exc_or_default(&env, res)
+}
+
+/// Returns the next value from the iterator. Returns null pointer when iteration is finished.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_VersionedMapIndexProxy_nativeEntriesIterNext(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) -> jobject {
+    let res = panic::catch_unwind(|| {
+        let iterWrapper = utils::cast_handle::<Iter>(iter_handle);
+        match iterWrapper.iter.next() {
+            Some(val) => {
+                let key: JObject = env.byte_array_from_slice(&val.0)?.into();
+                let value: JObject = env.byte_array_from_slice(&val.1)?.into();
+                Ok(env
+                    .new_object_by_id(
+                        &iterWrapper.element_class,
+                        iterWrapper.constructor_id,
+                        &[key.into(), value.into()],
+                    )?.into_inner())
+            }
+            None => Ok(ptr::null_mut()),
+        }
+    });
+    utils::unwrap_exc_or(&env, res, ptr::null_mut())
+}
+
+/// Destroys the underlying iterator object and frees memory.
+#[no_mangle]
+pub extern "system" fn Java_com_exonum_binding_storage_indices_VersionedMapIndexProxy_nativeEntriesIterFree(
+    env: JNIEnv,
+    _: JObject,
+    iter_handle: Handle,
+) {
+    utils::drop_handle::<Iter>(&env, iter_handle);
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum::storage::{Database, MemoryDB};
+
+    use super::*;
+
+    #[test]
+    fn entries_since() {
+        let db = MemoryDB::new();
+        let mut fork = db.fork();
+        {
+            let mut map = VersionedMap {
+                name: "test".to_owned(),
+                view: &mut fork,
+            };
+            map.put(&vec![1], vec![10]);
+            map.put(&vec![2], vec![20]);
+            map.put(&vec![3], vec![30]);
+            map.put(&vec![1], vec![11]);
+            map.remove(&vec![2]);
+
+            assert_eq!(
+                vec![(vec![3], vec![30]), (vec![1], vec![11])],
+                map.entries_since(0)
+            );
+            assert_eq!(vec![(vec![1], vec![11])], map.entries_since(3));
+            assert!(map.entries_since(4).is_empty());
+            assert!(map.entries_since(Sequence::max_value()).is_empty());
+        }
+        let values: MapIndex<_, Key, Value> = MapIndex::new("test", &fork);
+        assert_eq!(Some(vec![11]), values.get(&vec![1]));
+    }
+}
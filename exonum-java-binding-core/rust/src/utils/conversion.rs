@@ -14,11 +14,16 @@
 
 use exonum::crypto::Hash;
 use jni::objects::JString;
-use jni::sys::jbyteArray;
+use jni::sys::{jbyteArray, jlong, jobjectArray};
 use jni::JNIEnv;
 
+use utils::{
+    compose_family_id, throw_and_fail, INDEX_OUT_OF_BOUNDS_EXCEPTION, OUT_OF_MEMORY_ERROR,
+};
 use JniResult;
 
+const I64_SIZE: usize = 8;
+
 // Converts Java byte array to `Hash`. Panics if array has the wrong length.
 pub fn convert_to_hash(env: &JNIEnv, array: jbyteArray) -> JniResult<Hash> {
     let bytes = env.convert_byte_array(array)?;
@@ -30,6 +35,42 @@ pub fn convert_hash(env: &JNIEnv, hash: &Hash) -> JniResult<jbyteArray> {
     env.byte_array_from_slice(hash.as_ref())
 }
 
+/// Converts bytes to Java byte array.
+///
+/// If the array cannot be allocated, throws `OutOfMemoryError` with the size of the array
+/// instead of the error thrown by the JVM, which has no details.
+pub fn convert_bytes(env: &JNIEnv, bytes: &[u8]) -> JniResult<jbyteArray> {
+    match env.byte_array_from_slice(bytes) {
+        Err(_) if env.exception_check()? => {
+            env.exception_clear()?;
+            let message = format!("Unable to allocate a byte array of {} bytes", bytes.len());
+            throw_and_fail(env, OUT_OF_MEMORY_ERROR, message)
+        }
+        result => result,
+    }
+}
+
+/// Converts Java array of byte arrays into a `Vec` of byte vectors.
+pub fn convert_to_byte_arrays(env: &JNIEnv, array: jobjectArray) -> JniResult<Vec<Vec<u8>>> {
+    let len = env.get_array_length(array)?;
+    let mut arrays = Vec::with_capacity(len as usize);
+    for i in 0..len {
+        let element = env.get_object_array_element(array, i)?;
+        arrays.push(env.convert_byte_array(element.into_inner())?);
+        // The element is released right away, as the number of local references is limited.
+        env.delete_local_ref(element)?;
+    }
+    Ok(arrays)
+}
+
+/// Converts Java array of byte arrays into the id of an index in a family composed
+/// of these parts, see `compose_family_id`.
+pub fn convert_to_family_id(env: &JNIEnv, id_parts: jobjectArray) -> JniResult<Vec<u8>> {
+    let parts = convert_to_byte_arrays(env, id_parts)?;
+    let parts: Vec<&[u8]> = parts.iter().map(Vec::as_slice).collect();
+    Ok(compose_family_id(&parts))
+}
+
 /// Converts JNI `JString` into Rust `String`
 pub fn convert_to_string<'e, V>(env: &JNIEnv<'e>, val: V) -> JniResult<String>
 where
@@ -37,3 +78,103 @@ where
 {
     Ok(env.get_string(val.into())?.into())
 }
+
+/// Converts an 8-byte little-endian array into `i64`. Returns `None` if the array
+/// has the wrong length.
+pub fn convert_to_i64(bytes: &[u8]) -> Option<i64> {
+    if bytes.len() != I64_SIZE {
+        return None;
+    }
+    let value = bytes
+        .iter()
+        .rev()
+        .fold(0u64, |acc, &byte| (acc << 8) | u64::from(byte));
+    Some(value as i64)
+}
+
+/// Converts `i64` into an 8-byte little-endian array.
+pub fn convert_i64(value: i64) -> Vec<u8> {
+    (0..I64_SIZE).map(|i| (value >> (8 * i)) as u8).collect()
+}
+
+/// Converts `i64` key into an 8-byte big-endian array of its unsigned representation,
+/// so that the lexicographic order of arrays matches the numeric order of non-negative keys.
+pub fn convert_long_key(key: i64) -> Vec<u8> {
+    (0..I64_SIZE)
+        .rev()
+        .map(|i| (key >> (8 * i)) as u8)
+        .collect()
+}
+
+/// Converts Java element index into a list index. Returns an error message if the index
+/// is negative.
+pub fn jlong_to_index(value: jlong) -> Result<u64, String> {
+    if value < 0 {
+        return Err(format!("Index must not be negative: {}", value));
+    }
+    Ok(value as u64)
+}
+
+/// Converts Java element index into a list index, throwing `IndexOutOfBoundsException`
+/// if the index is negative.
+pub fn convert_to_index(env: &JNIEnv, value: jlong) -> JniResult<u64> {
+    match jlong_to_index(value) {
+        Ok(index) => Ok(index),
+        Err(message) => throw_and_fail(env, INDEX_OUT_OF_BOUNDS_EXCEPTION, message),
+    }
+}
+
+/// Throws `IndexOutOfBoundsException` if the index is not less than the size of the list.
+pub fn check_index_in_bounds(env: &JNIEnv, index: u64, len: u64) -> JniResult<()> {
+    if index >= len {
+        let message = format!(
+            "Index {} is out of bounds for the list of size {}",
+            index, len
+        );
+        return throw_and_fail(env, INDEX_OUT_OF_BOUNDS_EXCEPTION, message);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::i64;
+
+    #[test]
+    fn i64_round_trip() {
+        for &value in &[0, 1, -1, 42, i64::MIN, i64::MAX] {
+            assert_eq!(Some(value), convert_to_i64(&convert_i64(value)));
+        }
+    }
+
+    #[test]
+    fn i64_little_endian() {
+        assert_eq!(vec![1, 2, 0, 0, 0, 0, 0, 0], convert_i64(0x0201));
+    }
+
+    #[test]
+    fn i64_wrong_length() {
+        assert_eq!(None, convert_to_i64(&[]));
+        assert_eq!(None, convert_to_i64(&[0; 9]));
+    }
+
+    #[test]
+    fn long_key_order() {
+        assert_eq!(vec![0, 0, 0, 0, 0, 0, 2, 1], convert_long_key(0x0201));
+        assert!(convert_long_key(255) < convert_long_key(256));
+        assert!(convert_long_key(i64::MAX) < convert_long_key(-1));
+    }
+
+    #[test]
+    fn index_negative() {
+        assert!(jlong_to_index(-1).is_err());
+        assert!(jlong_to_index(i64::MIN).is_err());
+    }
+
+    #[test]
+    fn index_non_negative() {
+        assert_eq!(Ok(0), jlong_to_index(0));
+        assert_eq!(Ok(i64::MAX as u64), jlong_to_index(i64::MAX));
+    }
+}
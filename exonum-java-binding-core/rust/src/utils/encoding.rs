@@ -0,0 +1,76 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Text encodings of binary data for the diagnostic output.
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes the bytes as a lowercase hex string.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for &byte in bytes {
+        hex.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+        hex.push(HEX_DIGITS[(byte & 0xf) as usize] as char);
+    }
+    hex
+}
+
+/// Encodes the bytes as a base64 string with the standard alphabet and padding.
+pub fn to_base64(bytes: &[u8]) -> String {
+    let mut base64 = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let indices = [
+            b[0] >> 2,
+            (b[0] & 0x3) << 4 | b[1] >> 4,
+            (b[1] & 0xf) << 2 | b[2] >> 6,
+            b[2] & 0x3f,
+        ];
+        for (i, &index) in indices.iter().enumerate() {
+            if i <= chunk.len() {
+                base64.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                base64.push('=');
+            }
+        }
+    }
+    base64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex() {
+        assert_eq!("", to_hex(&[]));
+        assert_eq!("00ff1a", to_hex(&[0x00, 0xff, 0x1a]));
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!("", to_base64(b""));
+        assert_eq!("Zg==", to_base64(b"f"));
+        assert_eq!("Zm8=", to_base64(b"fo"));
+        assert_eq!("Zm9v", to_base64(b"foo"));
+        assert_eq!("Zm9vYmFy", to_base64(b"foobar"));
+        assert_eq!("/w==", to_base64(&[0xff]));
+    }
+}
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use jni::strings::JNIString;
 use jni::JNIEnv;
 
 use std::any::Any;
@@ -19,7 +20,22 @@ use std::error::Error;
 use std::result;
 use std::thread;
 
-use JniError;
+use {JniError, JniErrorKind, JniResult};
+
+/// `java.lang.ArithmeticException`, e.g., on an integer overflow.
+pub const ARITHMETIC_EXCEPTION: &str = "java/lang/ArithmeticException";
+/// `java.lang.IllegalArgumentException`.
+pub const ILLEGAL_ARGUMENT_EXCEPTION: &str = "java/lang/IllegalArgumentException";
+/// `java.lang.IndexOutOfBoundsException`.
+pub const INDEX_OUT_OF_BOUNDS_EXCEPTION: &str = "java/lang/IndexOutOfBoundsException";
+/// `java.io.IOException`.
+pub const IO_EXCEPTION: &str = "java/io/IOException";
+/// `java.util.NoSuchElementException`.
+pub const NO_SUCH_ELEMENT_EXCEPTION: &str = "java/util/NoSuchElementException";
+/// `java.lang.NullPointerException`.
+pub const NULL_POINTER_EXCEPTION: &str = "java/lang/NullPointerException";
+/// `java.lang.OutOfMemoryError`.
+pub const OUT_OF_MEMORY_ERROR: &str = "java/lang/OutOfMemoryError";
 
 type ExceptionResult<T> = thread::Result<result::Result<T, JniError>>;
 
@@ -53,6 +69,17 @@ pub fn unwrap_exc_or_default<T: Default>(env: &JNIEnv, res: ExceptionResult<T>)
     unwrap_exc_or(env, res, T::default())
 }
 
+/// Throws a Java exception of the given class with the message, and returns the error
+/// of the pending exception, so that the caller can return it right away.
+pub fn throw_and_fail<T, M: Into<JNIString>>(
+    env: &JNIEnv,
+    class: &str,
+    message: M,
+) -> JniResult<T> {
+    env.throw_new(class, message)?;
+    Err(JniErrorKind::JavaException.into())
+}
+
 // Calls a corresponding `JNIEnv` method, so exception will be thrown when execution returns to
 // the Java side.
 fn throw(env: &JNIEnv, description: &str) {
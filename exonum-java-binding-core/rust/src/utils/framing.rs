@@ -0,0 +1,174 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers for the length-prefixed binary formats used by the bindings.
+//!
+//! A frame is a byte sequence prefixed with its length encoded as a big-endian `u32`.
+
+const U32_SIZE: usize = 4;
+
+/// Appends `value` to the buffer as a big-endian `u32`.
+pub fn write_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend((0..U32_SIZE).rev().map(|i| (value >> (8 * i)) as u8));
+}
+
+/// Appends the given bytes to the buffer as a frame.
+pub fn write_frame(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    assert!(
+        bytes.len() <= u32::max_value() as usize,
+        "Frame is too large: {} bytes",
+        bytes.len()
+    );
+    write_u32(buffer, bytes.len() as u32);
+    buffer.extend_from_slice(bytes);
+}
+
+/// Composes the id of an index in a family from several parts: the id is the concatenation
+/// of the parts written as frames, so different sequences of parts never produce the same id.
+///
+/// All the bindings creating indices in a family from several parts use this encoding,
+/// so an index has the same id regardless of the binding.
+pub fn compose_family_id(parts: &[&[u8]]) -> Vec<u8> {
+    let mut id = Vec::new();
+    for part in parts {
+        write_frame(&mut id, part);
+    }
+    id
+}
+
+/// A reader of the data written with `write_u32` and `write_frame`.
+///
+/// All the read methods return `None` if the remaining data is too short.
+pub struct FrameReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> FrameReader<'a> {
+    /// Creates a reader of the given data.
+    pub fn new(data: &'a [u8]) -> Self {
+        FrameReader { data }
+    }
+
+    /// Returns `true` if there is no more data to read.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Reads a single byte.
+    pub fn read_u8(&mut self) -> Option<u8> {
+        self.read_bytes(1).map(|bytes| bytes[0])
+    }
+
+    /// Reads a big-endian `u32`.
+    pub fn read_u32(&mut self) -> Option<u32> {
+        self.read_bytes(U32_SIZE)
+            .map(|bytes| bytes.iter().fold(0, |acc, &b| (acc << 8) | u32::from(b)))
+    }
+
+    /// Reads a frame, returning its content without the length prefix.
+    pub fn read_frame(&mut self) -> Option<&'a [u8]> {
+        let len = self.read_u32()? as usize;
+        self.read_bytes(len)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn write_read_frames() {
+        let mut buffer = Vec::new();
+        write_u32(&mut buffer, 0x0102_0304);
+        write_frame(&mut buffer, b"key");
+        write_frame(&mut buffer, b"");
+        assert_eq!(&buffer[..4], &[1, 2, 3, 4]);
+
+        let mut reader = FrameReader::new(&buffer);
+        assert_eq!(Some(0x0102_0304), reader.read_u32());
+        assert_eq!(Some(&b"key"[..]), reader.read_frame());
+        assert_eq!(Some(&b""[..]), reader.read_frame());
+        assert!(reader.is_empty());
+        assert_eq!(None, reader.read_u8());
+    }
+
+    #[test]
+    fn compose_family_id_of_parts() {
+        assert_eq!(Vec::<u8>::new(), compose_family_id(&[]));
+        assert_eq!(
+            vec![0, 0, 0, 1, 7, 0, 0, 0, 0],
+            compose_family_id(&[&[7], &[]])
+        );
+    }
+
+    #[test]
+    fn distinct_parts_produce_distinct_family_ids() {
+        // All the sequences of up to three parts of up to two bytes from a small alphabet,
+        // which include the sequences differing only in how the bytes are split into parts.
+        let mut part_values: Vec<Vec<u8>> = vec![vec![]];
+        for &a in &[0u8, 1] {
+            part_values.push(vec![a]);
+            for &b in &[0u8, 1] {
+                part_values.push(vec![a, b]);
+            }
+        }
+        let mut sequences: Vec<Vec<Vec<u8>>> = vec![vec![]];
+        for len in 1..4 {
+            let shorter: Vec<Vec<Vec<u8>>> = sequences
+                .iter()
+                .filter(|sequence| sequence.len() == len - 1)
+                .cloned()
+                .collect();
+            for sequence in shorter {
+                for part in &part_values {
+                    let mut longer = sequence.clone();
+                    longer.push(part.clone());
+                    sequences.push(longer);
+                }
+            }
+        }
+
+        let mut ids = HashSet::new();
+        for sequence in &sequences {
+            let parts: Vec<&[u8]> = sequence.iter().map(Vec::as_slice).collect();
+            assert!(
+                ids.insert(compose_family_id(&parts)),
+                "Duplicate id for {:?}",
+                sequence
+            );
+        }
+        assert_eq!(1 + 7 + 49 + 343, ids.len());
+    }
+
+    #[test]
+    fn read_truncated_frame() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, b"value");
+        buffer.pop();
+
+        let mut reader = FrameReader::new(&buffer);
+        assert_eq!(None, reader.read_frame());
+    }
+}
@@ -73,6 +73,26 @@ pub fn cast_handle<T>(handle: Handle) -> &'static mut T {
     unsafe { &mut *ptr }
 }
 
+/// Returns a description of the object identified by the given handle, made by `describe`,
+/// for the diagnostic purposes.
+///
+/// The object is accessed only if the resource manager knows the handle as one of type `T`,
+/// so an invalid or freed handle is reported as such instead. Without the "resource-manager"
+/// feature the validity of handles cannot be checked, and the object is never accessed.
+pub fn describe_handle<T: 'static, F>(handle: Handle, describe: F) -> String
+where
+    F: FnOnce(&T) -> String,
+{
+    match resource_manager::is_valid_handle::<T>(handle) {
+        Some(true) => describe(cast_handle::<T>(handle)),
+        Some(false) => format!("'{:X}': invalid or freed handle", handle),
+        None => format!(
+            "'{:X}': unknown handle, the handles are not tracked without \"resource-manager\" feature",
+            handle
+        ),
+    }
+}
+
 /// Destroys the Java-owned native object identified by the given handle.
 ///
 /// # Panics
@@ -0,0 +1,80 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The verbosity of the native log messages of the bindings, adjustable at runtime.
+//!
+//! The level filters the messages of the bindings only, e.g., the panic reports,
+//! and not the messages of Exonum or the other libraries.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A level of the native log messages, from the quietest to the most verbose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    /// No messages.
+    Off,
+    /// Errors, e.g., the panic reports.
+    Error,
+    /// Warnings and errors.
+    Warn,
+    /// Informational messages, warnings and errors.
+    Info,
+    /// All the messages, including the debugging ones.
+    Debug,
+}
+
+static LEVEL: AtomicUsize = AtomicUsize::new(LogLevel::Error as usize);
+
+/// Sets the level of the native log messages, which is `Error` by default.
+pub fn set_log_level(level: LogLevel) {
+    LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+/// Returns the level of the native log messages.
+pub fn log_level() -> LogLevel {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => LogLevel::Off,
+        1 => LogLevel::Error,
+        2 => LogLevel::Warn,
+        3 => LogLevel::Info,
+        _ => LogLevel::Debug,
+    }
+}
+
+/// Returns `true` if the messages of the given level must be logged.
+pub fn log_enabled(level: LogLevel) -> bool {
+    level != LogLevel::Off && level <= log_level()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_level() {
+        assert_eq!(LogLevel::Error, log_level());
+        assert!(log_enabled(LogLevel::Error));
+        assert!(!log_enabled(LogLevel::Warn));
+
+        set_log_level(LogLevel::Info);
+        assert!(log_enabled(LogLevel::Warn));
+        assert!(!log_enabled(LogLevel::Debug));
+
+        set_log_level(LogLevel::Off);
+        assert!(!log_enabled(LogLevel::Error));
+        assert!(!log_enabled(LogLevel::Off));
+
+        set_log_level(LogLevel::Error);
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Counts and latencies of the storage operations.
+//!
+//! The metrics are disabled by default; when disabled, measuring an operation costs
+//! a single atomic load.
+
+use serde_json;
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_BOOL_INIT, ATOMIC_USIZE_INIT};
+use std::time::Instant;
+
+/// A measured storage operation.
+#[derive(Debug, Clone, Copy)]
+pub enum Operation {
+    /// Getting a value.
+    Get,
+    /// Putting a value.
+    Put,
+    /// Removing a value.
+    Remove,
+    /// Getting the next element of an iterator.
+    IterNext,
+}
+
+const OPERATIONS: [Operation; 4] = [
+    Operation::Get,
+    Operation::Put,
+    Operation::Remove,
+    Operation::IterNext,
+];
+
+impl Operation {
+    fn name(self) -> &'static str {
+        match self {
+            Operation::Get => "get",
+            Operation::Put => "put",
+            Operation::Remove => "remove",
+            Operation::IterNext => "iter_next",
+        }
+    }
+}
+
+static ENABLED: AtomicBool = ATOMIC_BOOL_INIT;
+static COUNTS: [AtomicUsize; 4] = [
+    ATOMIC_USIZE_INIT,
+    ATOMIC_USIZE_INIT,
+    ATOMIC_USIZE_INIT,
+    ATOMIC_USIZE_INIT,
+];
+static TOTAL_NANOS: [AtomicUsize; 4] = [
+    ATOMIC_USIZE_INIT,
+    ATOMIC_USIZE_INIT,
+    ATOMIC_USIZE_INIT,
+    ATOMIC_USIZE_INIT,
+];
+
+#[derive(Serialize)]
+struct OperationMetrics {
+    count: usize,
+    total_nanos: usize,
+}
+
+/// Enables or disables recording of the metrics. The recorded values are kept
+/// when the metrics are disabled.
+pub fn set_metrics_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Executes `f`, recording its latency as the latency of `operation` if the metrics are enabled.
+pub fn measure<T, F: FnOnce() -> T>(operation: Operation, f: F) -> T {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    let nanos = elapsed.as_secs() as usize * 1_000_000_000 + elapsed.subsec_nanos() as usize;
+    COUNTS[operation as usize].fetch_add(1, Ordering::Relaxed);
+    TOTAL_NANOS[operation as usize].fetch_add(nanos, Ordering::Relaxed);
+    result
+}
+
+/// Returns the recorded metrics as a JSON object, which maps the name of each operation
+/// to its `count` and `total_nanos`.
+pub fn metrics_json() -> String {
+    let metrics: BTreeMap<_, _> = OPERATIONS
+        .iter()
+        .map(|&operation| {
+            let metrics = OperationMetrics {
+                count: COUNTS[operation as usize].load(Ordering::Relaxed),
+                total_nanos: TOTAL_NANOS[operation as usize].load(Ordering::Relaxed),
+            };
+            (operation.name(), metrics)
+        }).collect();
+    serde_json::to_string(&metrics).expect("Unable to serialize metrics")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measure_when_enabled() {
+        set_metrics_enabled(true);
+        assert_eq!(42, measure(Operation::Remove, || 42));
+        set_metrics_enabled(false);
+        measure(Operation::Remove, || ());
+
+        let metrics: serde_json::Value = serde_json::from_str(&metrics_json()).unwrap();
+        assert_eq!(1, metrics["remove"]["count"]);
+        assert_eq!(0, metrics["get"]["count"]);
+    }
+}
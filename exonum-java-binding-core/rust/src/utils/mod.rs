@@ -16,20 +16,40 @@
 #![deny(non_snake_case)]
 
 mod conversion;
+mod encoding;
 mod errors;
 mod exception;
+mod framing;
 mod handle;
 mod jni;
+mod log_level;
+mod metrics;
 mod pair_iter;
+mod panic_hook;
 mod resource_manager;
 
-pub use self::conversion::{convert_hash, convert_to_hash, convert_to_string};
+pub use self::conversion::{
+    check_index_in_bounds, convert_bytes, convert_hash, convert_i64, convert_long_key,
+    convert_to_byte_arrays, convert_to_family_id, convert_to_hash, convert_to_i64,
+    convert_to_index, convert_to_string, jlong_to_index,
+};
+pub use self::encoding::{to_base64, to_hex};
 pub use self::errors::{
     check_error_on_exception, describe_java_exception, get_and_clear_java_exception,
     panic_on_exception, unwrap_jni, unwrap_jni_verbose,
 };
-pub use self::exception::{any_to_string, unwrap_exc_or, unwrap_exc_or_default};
-pub use self::handle::{as_handle, cast_handle, drop_handle, to_handle, Handle};
+pub use self::exception::{
+    any_to_string, throw_and_fail, unwrap_exc_or, unwrap_exc_or_default, ARITHMETIC_EXCEPTION,
+    ILLEGAL_ARGUMENT_EXCEPTION, INDEX_OUT_OF_BOUNDS_EXCEPTION, IO_EXCEPTION,
+    NO_SUCH_ELEMENT_EXCEPTION, NULL_POINTER_EXCEPTION, OUT_OF_MEMORY_ERROR,
+};
+pub use self::framing::{compose_family_id, write_frame, write_u32, FrameReader};
+pub use self::handle::{as_handle, cast_handle, describe_handle, drop_handle, to_handle, Handle};
 pub use self::jni::{get_class_name, get_exception_message};
+pub use self::log_level::{log_enabled, log_level, set_log_level, LogLevel};
+pub use self::metrics::{measure, metrics_json, set_metrics_enabled, Operation};
 pub use self::pair_iter::PairIter;
+pub use self::panic_hook::{
+    install_panic_hook, panic_log_target, set_panic_log_target, PanicLogTarget,
+};
 pub use self::resource_manager::known_handles;
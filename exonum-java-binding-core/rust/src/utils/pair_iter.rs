@@ -15,24 +15,40 @@
 use jni::objects::{GlobalRef, JMethodID};
 use jni::JNIEnv;
 
+use std::iter::Peekable;
 use std::mem;
 
 use JniResult;
 
+/// The signature of the element constructor used by default (taking a key and a value).
+const DEFAULT_CONSTRUCTOR_SIGNATURE: &str = "([B[B)V";
+
 pub struct PairIter<InnerIter: Iterator> {
-    pub iter: InnerIter,
+    /// The wrapped iterator, peekable to allow looking at the next element without consuming it.
+    pub iter: Peekable<InnerIter>,
     pub element_class: GlobalRef,
     pub constructor_id: JMethodID<'static>,
 }
 
 impl<InnerIter: Iterator> PairIter<InnerIter> {
     pub fn new(env: &JNIEnv, iter: InnerIter, class_name: &str) -> JniResult<Self> {
+        Self::with_constructor(env, iter, class_name, DEFAULT_CONSTRUCTOR_SIGNATURE)
+    }
+
+    /// Creates an iterator with elements of the given class, created with the constructor
+    /// with the given signature. The constructor must accept the key and the value
+    /// as two byte arrays, e.g., `(Ljava/lang/Object;Ljava/lang/Object;)V`.
+    pub fn with_constructor(
+        env: &JNIEnv,
+        iter: InnerIter,
+        class_name: &str,
+        signature: &str,
+    ) -> JniResult<Self> {
         let class = env.find_class(class_name)?;
         let element_class = env.new_global_ref(class.into())?;
-        let signature = "([B[B)V";
         let id = env.get_method_id(class_name, "<init>", signature)?;
         Ok(PairIter {
-            iter,
+            iter: iter.peekable(),
             element_class,
             constructor_id: unsafe { mem::transmute(id) },
         })
@@ -0,0 +1,118 @@
+// Copyright 2018 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A panic hook reporting the native panics before they are converted to Java exceptions.
+//!
+//! The panics are reported to a configurable target, so the applications can route
+//! them to their own logging.
+
+use backtrace::Backtrace;
+
+use std::env;
+use std::panic::{self, PanicInfo};
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+use std::sync::{Once, ONCE_INIT};
+
+use super::log_level::{log_enabled, LogLevel};
+
+/// A target of the panic reports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PanicLogTarget {
+    /// The standard error stream, as with the default hook, unless disabled with
+    /// `set_log_level`: the message and the location of the panic, and a backtrace
+    /// if `RUST_BACKTRACE` is set.
+    Stderr,
+    /// The `log` crate, at the error level, unless disabled with `set_log_level`:
+    /// the message and the location of the panic, and a backtrace if `RUST_BACKTRACE` is set.
+    Log,
+}
+
+static TARGET: AtomicUsize = ATOMIC_USIZE_INIT;
+static INSTALL_HOOK: Once = ONCE_INIT;
+
+/// Sets the target of the panic reports, which is `Stderr` by default.
+pub fn set_panic_log_target(target: PanicLogTarget) {
+    TARGET.store(target as usize, Ordering::Relaxed);
+}
+
+/// Returns the target of the panic reports.
+pub fn panic_log_target() -> PanicLogTarget {
+    match TARGET.load(Ordering::Relaxed) {
+        0 => PanicLogTarget::Stderr,
+        _ => PanicLogTarget::Log,
+    }
+}
+
+/// Installs the panic hook reporting the panics to the `panic_log_target`, if the errors
+/// are enabled by the `log_level`. Only the first call has an effect.
+pub fn install_panic_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !log_enabled(LogLevel::Error) {
+                return;
+            }
+            match panic_log_target() {
+                PanicLogTarget::Stderr => default_hook(info),
+                PanicLogTarget::Log => {
+                    if backtrace_enabled() {
+                        error!("{}\n{:?}", describe_panic(info), Backtrace::new());
+                    } else {
+                        error!("{}", describe_panic(info));
+                    }
+                }
+            }
+        }));
+    });
+}
+
+/// Returns `true` if `RUST_BACKTRACE` is set to a value other than `0`,
+/// like the default hook does.
+fn backtrace_enabled() -> bool {
+    env::var_os("RUST_BACKTRACE").map_or(false, |value| value.to_str() != Some("0"))
+}
+
+fn describe_panic(info: &PanicInfo) -> String {
+    let payload = info.payload();
+    let message = if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.as_str()
+    } else {
+        "Box<Any>"
+    };
+    match info.location() {
+        Some(location) => format!(
+            "Native code panicked at '{}', {}:{}",
+            message,
+            location.file(),
+            location.line()
+        ),
+        None => format!("Native code panicked at '{}'", message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_target() {
+        assert_eq!(PanicLogTarget::Stderr, panic_log_target());
+        set_panic_log_target(PanicLogTarget::Log);
+        assert_eq!(PanicLogTarget::Log, panic_log_target());
+        set_panic_log_target(PanicLogTarget::Stderr);
+        assert_eq!(PanicLogTarget::Stderr, panic_log_target());
+    }
+}
@@ -165,6 +165,18 @@ pub fn known_handles() -> usize {
         .len()
 }
 
+/// Returns `Some(true)` if the given handle is known and has the given type,
+/// and `Some(false)` otherwise. Never returns `None`, unlike the stub that does not
+/// track the handles.
+pub fn is_valid_handle<T: 'static>(handle: Handle) -> Option<bool> {
+    let valid = HANDLES_MAP
+        .read()
+        .expect("Unable to obtain read-lock")
+        .get(&handle)
+        .map_or(false, |info| info.object_type == TypeId::of::<T>());
+    Some(valid)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,6 +193,7 @@ mod tests {
     const DUPLICATED_HANDLE: Handle = 4000;
     const WRONG_TYPE_HANDLE: Handle = 5000;
     const WRONG_OWNERSHIP_HANDLE: Handle = 6000;
+    const VALIDITY_HANDLE: Handle = 7000;
 
     #[test]
     fn manage_handles() {
@@ -272,6 +285,17 @@ mod tests {
         check_handle::<OtherT>(WRONG_TYPE_HANDLE);
     }
 
+    #[test]
+    fn is_valid() {
+        enum OtherT {}
+        assert_eq!(Some(false), is_valid_handle::<T>(VALIDITY_HANDLE));
+        add_handle::<T>(VALIDITY_HANDLE);
+        assert_eq!(Some(true), is_valid_handle::<T>(VALIDITY_HANDLE));
+        assert_eq!(Some(false), is_valid_handle::<OtherT>(VALIDITY_HANDLE));
+        remove_handle::<T>(VALIDITY_HANDLE);
+        assert_eq!(Some(false), is_valid_handle::<T>(VALIDITY_HANDLE));
+    }
+
     #[test]
     #[should_panic(expected = "handle should be")]
     fn check_wrong_ownrship_handle() {
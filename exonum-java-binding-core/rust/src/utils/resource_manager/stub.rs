@@ -23,3 +23,6 @@ pub fn check_handle<T: 'static>(_: Handle) {}
 pub fn known_handles() -> usize {
     0
 }
+pub fn is_valid_handle<T: 'static>(_: Handle) -> Option<bool> {
+    None
+}
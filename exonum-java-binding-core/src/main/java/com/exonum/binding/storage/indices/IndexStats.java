@@ -0,0 +1,65 @@
+/*
+ * Copyright 2018 The Exonum Team
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+package com.exonum.binding.storage.indices;
+
+import java.util.Optional;
+
+/**
+ * A summary of a map index, collected in a single pass over its entries.
+ */
+public final class IndexStats {
+  private final long size;
+  private final long approxSizeBytes;
+  private final byte[] firstKey;
+  private final byte[] lastKey;
+
+  @SuppressWarnings("unused")  // native API
+  IndexStats(long size, long approxSizeBytes, byte[] firstKey, byte[] lastKey) {
+    this.size = size;
+    this.approxSizeBytes = approxSizeBytes;
+    this.firstKey = firstKey;
+    this.lastKey = lastKey;
+  }
+
+  /**
+   * Returns the number of entries in the index.
+   */
+  public long getSize() {
+    return size;
+  }
+
+  /**
+   * Returns an estimate of the number of bytes the index occupies in the storage.
+   */
+  public long getApproxSizeBytes() {
+    return approxSizeBytes;
+  }
+
+  /**
+   * Returns the smallest key of the index, or {@code Optional.empty()} if it is empty.
+   */
+  public Optional<byte[]> getFirstKey() {
+    return Optional.ofNullable(firstKey).map(byte[]::clone);
+  }
+
+  /**
+   * Returns the largest key of the index, or {@code Optional.empty()} if it is empty.
+   */
+  public Optional<byte[]> getLastKey() {
+    return Optional.ofNullable(lastKey).map(byte[]::clone);
+  }
+}
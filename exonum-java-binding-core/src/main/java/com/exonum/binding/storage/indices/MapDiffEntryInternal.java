@@ -0,0 +1,44 @@
+/*
+ * Copyright 2018 The Exonum Team
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+package com.exonum.binding.storage.indices;
+
+import static com.exonum.binding.storage.indices.StoragePreconditions.checkStorageKey;
+
+/**
+ * A difference of the entries with the same key in two map indices,
+ * see {@code MapIndexProxy#nativeDiff}.
+ */
+final class MapDiffEntryInternal {
+  static final int CHANGE_ADDED = 0;
+  static final int CHANGE_REMOVED = 1;
+  static final int CHANGE_MODIFIED = 2;
+
+  final int change;
+  final byte[] key;
+  /** The value in the first index, or null if the entry was added. */
+  final byte[] oldValue;
+  /** The value in the second index, or null if the entry was removed. */
+  final byte[] newValue;
+
+  @SuppressWarnings("unused")  // native API
+  MapDiffEntryInternal(int change, byte[] key, byte[] oldValue, byte[] newValue) {
+    this.change = change;
+    this.key = checkStorageKey(key);
+    this.oldValue = oldValue;
+    this.newValue = newValue;
+  }
+}